@@ -1,6 +1,7 @@
 // --- Global State ---
 let chainData = [];
-const API_URL = "http://127.0.0.1:3000/api/chain";
+const API_BASE = "http://127.0.0.1:3000/api";
+const API_URL = `${API_BASE}/chain`;
 const POLLING_INTERVAL = 3000; // Check for new data every 3 seconds
 
 // --- D3 Visualization Logic ---
@@ -31,6 +32,93 @@ async function fetchChainData() {
     }
 }
 
+// --- Merkle Proof Checking (mirrors src/core_types/merkle.rs) ---
+const hexToBytes = hex => new Uint8Array(hex.match(/../g).map(byte => parseInt(byte, 16)));
+const bytesToHex = bytes => Array.from(bytes, byte => byte.toString(16).padStart(2, "0")).join("");
+
+async function nodeHash(left, right) {
+    const data = new Uint8Array(1 + left.length + right.length);
+    data[0] = 0x01;
+    data.set(left, 1);
+    data.set(right, 1 + left.length);
+    return new Uint8Array(await crypto.subtle.digest("SHA-256", data));
+}
+
+// Canonical transaction bytes (mirrors src/core_types/encoding.rs): strings
+// are a u32 big-endian length and UTF-8 bytes, integers big-endian u64.
+function encodeTransaction(tx) {
+    const parts = [];
+    const str = text => {
+        const bytes = new TextEncoder().encode(text);
+        const length = new Uint8Array(4);
+        new DataView(length.buffer).setUint32(0, bytes.length);
+        parts.push(length, bytes);
+    };
+    const u64 = value => {
+        const bytes = new Uint8Array(8);
+        new DataView(bytes.buffer).setBigUint64(0, BigInt(value));
+        parts.push(bytes);
+    };
+    str(tx.sender);
+    str(tx.recipient);
+    u64(tx.amount);
+    str(tx.public_key);
+    u64(tx.nonce);
+    str(tx.signature);
+    const data = new Uint8Array(parts.reduce((total, part) => total + part.length, 0));
+    let offset = 0;
+    for (const part of parts) {
+        data.set(part, offset);
+        offset += part.length;
+    }
+    return data;
+}
+
+async function leafHash(data) {
+    const prefixed = new Uint8Array(1 + data.length);
+    prefixed[0] = 0x00;
+    prefixed.set(data, 1);
+    return new Uint8Array(await crypto.subtle.digest("SHA-256", prefixed));
+}
+
+// The leaf is hashed here from the transaction, never taken from the node.
+async function verifyMerkleProof(tx, proof, expectedRoot) {
+    let current = await leafHash(encodeTransaction(tx));
+    for (const step of proof.path) {
+        const sibling = hexToBytes(step.hash);
+        current = step.side === "left" ? await nodeHash(sibling, current) : await nodeHash(current, sibling);
+    }
+    return bytesToHex(current) === expectedRoot;
+}
+
+// The chain listing only carries headers, so the body is fetched on hover.
+// The block reward (always the last transaction) is then checked against the
+// header's tx_root using only the transaction and its Merkle path.
+async function loadBlockDetails(entry) {
+    const details = document.getElementById("block-details");
+    const status = document.getElementById("reward-proof-status");
+    try {
+        const block = await (await fetch(`${API_BASE}/blocks/${entry.header.index}`)).json();
+        const proof = block.body.proof;
+        details.innerHTML = `
+            <strong>Author:</strong> ${proof.author}<br>
+            <strong>Proof (${proof.language}):</strong>
+            <pre class="bg-gray-800 p-1 rounded mt-1 text-xs">${proof.code.length > 50 ? proof.code.substring(0, 50) + '...' : proof.code}</pre>
+        `;
+        if (!status) return;
+        const position = block.body.transactions.length - 1;
+        const response = await (await fetch(`${API_BASE}/blocks/${entry.header.index}/proof/tx/${position}`)).json();
+        const tx = response.transaction;
+        const included = response.position === position
+            && tx.sender === "0"
+            && await verifyMerkleProof(tx, response.proof, entry.header.tx_root);
+        status.textContent = included ? `Reward to ${tx.recipient.substring(0, 12)}... verified` : "Reward proof INVALID";
+        status.className = included ? "text-green-400" : "text-red-400";
+    } catch (error) {
+        details.textContent = "Could not load block body";
+    }
+}
+
 function renderChain() {
     const { width } = svgContainer.node().getBoundingClientRect();
     const height = 500;
@@ -73,12 +161,12 @@ function renderChain() {
         .attr("height", blockHeight)
         .attr("rx", 8)
         .attr("ry", 8)
-        .attr("fill", d => d.index === 0 ? "#4b5563" : "#16a34a")
+        .attr("fill", d => d.header.index === 0 ? "#4b5563" : "#16a34a")
         .attr("stroke", "#9ca3af")
         .attr("stroke-width", 1);
 
     blocks.append("text")
-        .text(d => `Block #${d.index}`)
+        .text(d => `Block #${d.header.index}`)
         .attr("x", blockWidth / 2)
         .attr("y", 25)
         .attr("text-anchor", "middle")
@@ -86,7 +174,7 @@ function renderChain() {
         .style("font-weight", "600");
 
     blocks.append("text")
-        .text(d => `Lang: ${d.proof.language}`)
+        .text(d => new Date(d.header.timestamp * 1000).toLocaleTimeString())
         .attr("x", blockWidth / 2)
         .attr("y", 50)
         .attr("text-anchor", "middle")
@@ -106,18 +194,19 @@ function renderChain() {
     blocks.on("mouseover", (event, d) => {
         tooltip.transition().duration(200).style("opacity", .95);
         tooltip.html(`
-            <strong>Index:</strong> ${d.index}<br>
-            <strong>Timestamp:</strong> ${new Date(d.timestamp * 1000).toLocaleString()}<br>
-            <strong>Author:</strong> ${d.proof.author}<br>
+            <strong>Index:</strong> ${d.header.index}<br>
+            <strong>Timestamp:</strong> ${new Date(d.header.timestamp * 1000).toLocaleString()}<br>
             <hr class="my-1 border-gray-500">
             <strong>Hash:</strong> <span class="text-green-400">${d.hash.substring(0, 20)}...</span><br>
-            <strong>Prev Hash:</strong> <span class="text-yellow-400">${d.previous_hash.substring(0, 20)}...</span><br>
+            <strong>Prev Hash:</strong> <span class="text-yellow-400">${d.header.previous_hash.substring(0, 20)}...</span><br>
+            <strong>Tx Root:</strong> <span class="text-blue-400">${d.header.tx_root.substring(0, 20)}...</span><br>
+            ${d.header.index === 0 ? "" : '<span id="reward-proof-status" class="text-gray-400">Checking reward proof...</span><br>'}
             <hr class="my-1 border-gray-500">
-            <strong>Proof (${d.proof.language}):</strong>
-            <pre class="bg-gray-800 p-1 rounded mt-1 text-xs">${d.proof.code.length > 50 ? d.proof.code.substring(0, 50) + '...' : d.proof.code}</pre>
+            <div id="block-details" class="text-gray-400">Loading block...</div>
         `)
         .style("left", (event.pageX + 15) + "px")
         .style("top", (event.pageY - 28) + "px");
+        loadBlockDetails(d);
     })
     .on("mouseout", () => {
         tooltip.transition().duration(500).style("opacity", 0);
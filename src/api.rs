@@ -1,21 +1,334 @@
-use axum::{routing::get, Router, response::Json};
+use axum::{routing::{get, post}, Router, response::Json, http::StatusCode, extract::{Path, State}, body::Bytes};
 use std::sync::{Arc, Mutex};
 use tower_http::cors::{CorsLayer, Any};
 use crate::app_state::AppState;
+use crate::challenges::registry::ChallengeRegistry;
+use crate::challenges::challenge_trait::ChallengeSolution;
+use crate::challenges::verifier::VerificationOracle;
+use crate::core_types::blockchain::{block_reward, AcceptOutcome, Block, ChainError};
+use crate::core_types::encoding::Canonical;
+use crate::core_types::merkle::MerkleProof;
+use crate::core_types::transaction::Transaction;
+use crate::jobs::{JobId, JobQueue};
+use crate::similarity::MatchSource;
+use crate::storage::state_file;
+use crate::verifiers::VerifierSet;
+use crate::wallet::Wallet;
+use serde::Deserialize;
+
+// Everything the handlers need: the shared node state, this node's wallet,
+// the verification job queue, the oracle and the verifier quorum.
+#[derive(Clone)]
+pub struct ApiState {
+    pub app: Arc<Mutex<AppState>>,
+    pub wallet: Arc<Wallet>,
+    pub jobs: Arc<JobQueue>,
+    pub oracle: Arc<VerificationOracle>,
+    pub verifiers: Arc<VerifierSet>,
+}
 
 // This function will be our API endpoint handler.
 // It safely locks the shared state to read the data.
-async fn get_chain_data(state: axum::extract::State<Arc<Mutex<AppState>>>) -> Json<serde_json::Value> {
-    let state_lock = state.lock().unwrap();
+async fn get_chain_data(State(state): State<ApiState>) -> Json<serde_json::Value> {
+    // Headers only: solution code stays out of the listing. Full blocks
+    // are served one at a time by /api/blocks/:index.
+    let state_lock = state.app.lock().unwrap();
+    let headers: Vec<_> = state_lock.blockchain.chain.iter()
+        .map(|block| serde_json::json!({ "hash": block.hash, "header": block.header }))
+        .collect();
+    Json(serde_json::json!({
+        "chain": headers
+    }))
+}
+
+// Carries the transaction itself, so a client can hash it and check the
+// leaf instead of trusting `proof.leaf_hash`. The amount is in base units
+// and sent as a string, since JavaScript numbers cannot hold every u64.
+fn proof_response(block: &Block, position: usize, tx: &Transaction, proof: MerkleProof) -> (StatusCode, Json<serde_json::Value>) {
+    (StatusCode::OK, Json(serde_json::json!({
+        "block_index": block.header.index,
+        "block_hash": block.hash,
+        "tx_root": block.header.tx_root,
+        "position": position,
+        "transaction": {
+            "sender": tx.sender,
+            "recipient": tx.recipient,
+            "amount": tx.amount.units().to_string(),
+            "public_key": tx.public_key,
+            "nonce": tx.nonce,
+            "signature": tx.signature,
+        },
+        "proof": proof,
+    })))
+}
+
+fn not_found(message: String) -> (StatusCode, Json<serde_json::Value>) {
+    (StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": message })))
+}
+
+// A full block, header and body.
+async fn get_block(
+    State(state): State<ApiState>,
+    Path(index): Path<u64>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let state_lock = state.app.lock().unwrap();
+    match state_lock.blockchain.chain.get(index as usize) {
+        Some(block) => (StatusCode::OK, Json(serde_json::json!(block))),
+        None => not_found(format!("block #{} not found", index)),
+    }
+}
+
+// The solution is committed to directly by `header.solution_hash`, so its
+// proof of inclusion is the header itself plus the solution to hash.
+async fn get_solution_proof(
+    State(state): State<ApiState>,
+    Path(index): Path<u64>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let state_lock = state.app.lock().unwrap();
+    match state_lock.blockchain.chain.get(index as usize) {
+        Some(block) => (StatusCode::OK, Json(serde_json::json!({
+            "block_index": block.header.index,
+            "block_hash": block.hash,
+            "header": block.header,
+            "solution": block.body.proof,
+        }))),
+        None => not_found(format!("block #{} not found", index)),
+    }
+}
+
+// Inclusion proof for one transaction, e.g. a reward or payment.
+async fn get_transaction_proof(
+    State(state): State<ApiState>,
+    Path((index, position)): Path<(u64, usize)>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let state_lock = state.app.lock().unwrap();
+    let Some(block) = state_lock.blockchain.chain.get(index as usize) else {
+        return not_found(format!("block #{} not found", index));
+    };
+    match (block.body.transactions.get(position), block.prove_transaction(position)) {
+        (Some(tx), Some(proof)) => proof_response(block, position, tx, proof),
+        _ => not_found(format!("block #{} has no transaction {}", index, position)),
+    }
+}
+
+// Accepts a block mined by another node, sent in its canonical binary
+// encoding. Its solution is not run again: the block must carry a quorum of
+// attestations from registered verifiers, and the chain checks that it
+// answers the challenge selected for its parent. It then either extends our
+// tip, waits on a side branch, or wins fork choice and triggers a
+// reorganization.
+async fn submit_block(
+    State(state): State<ApiState>,
+    body: Bytes,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let block = match Block::from_canonical_bytes(&body) {
+        Ok(block) => block,
+        Err(err) => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": err.to_string() }))),
+    };
+    if let Err(reason) = state.verifiers.check_block(&block) {
+        return (StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::json!({ "error": reason })));
+    }
+    let hash = block.hash.clone();
+    let mut state_lock = state.app.lock().unwrap();
+    match state_lock.blockchain.accept_block(block) {
+        Ok(AcceptOutcome::Extended) => {
+            let tip = state_lock.blockchain.last_block().clone();
+            state_lock.credit_block(&tip);
+            state_file::save(&state_lock);
+            (StatusCode::OK, Json(serde_json::json!({ "accepted": hash, "outcome": "extended" })))
+        }
+        Ok(AcceptOutcome::SideBranch) => (StatusCode::OK, Json(serde_json::json!({ "accepted": hash, "outcome": "side_branch" }))),
+        Ok(AcceptOutcome::Reorganized(reorg)) => {
+            state_lock.apply_reorg(&reorg);
+            state_file::save(&state_lock);
+            (StatusCode::OK, Json(serde_json::json!({
+                "accepted": hash,
+                "outcome": "reorganized",
+                "disconnected": reorg.disconnected.len(),
+                "connected": reorg.connected.len(),
+            })))
+        }
+        Err(err @ ChainError::StoreFailed { .. }) => (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": err.to_string() }))),
+        Err(err) => (StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::json!({ "error": err.to_string() }))),
+    }
+}
+
+// Queues a signed transfer for the next mined block. This is the only way
+// to spend over the API: the node's own wallet is spent from the CLI.
+async fn submit_transaction(
+    State(state): State<ApiState>,
+    Json(tx): Json<Transaction>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let mut state_lock = state.app.lock().unwrap();
+    match state_lock.blockchain.add_transaction(tx) {
+        Ok(()) => {
+            state_file::save(&state_lock);
+            (StatusCode::OK, Json(serde_json::json!({ "queued": true })))
+        }
+        Err(err) => (StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::json!({ "error": err.to_string() }))),
+    }
+}
+
+// Returns the confirmed balance of an address, the balance once queued
+// transactions are mined, and the nonce its next transfer must use.
+async fn get_balance(State(state): State<ApiState>, Path(address): Path<String>) -> Json<serde_json::Value> {
+    let state_lock = state.app.lock().unwrap();
+    let pending = state_lock.blockchain.pending_accounts();
+    Json(serde_json::json!({
+        "address": address,
+        "balance": state_lock.blockchain.confirmed_accounts().balance(&address).to_string(),
+        "pending_balance": pending.balance(&address).to_string(),
+        "next_nonce": pending.next_nonce(&address),
+    }))
+}
+
+#[derive(Deserialize)]
+struct JobRequest {
+    challenge_id: String,
+    language: String,
+    code: String,
+    author: String,
+    /// Defaults to this node's wallet.
+    reward_address: Option<String>,
+}
+
+// Queues a solution for verification and returns its job id right away.
+// A passing solution is mined into a block by the worker that checked it.
+async fn submit_job(
+    State(state): State<ApiState>,
+    Json(request): Json<JobRequest>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let solution = ChallengeSolution {
+        challenge_id: request.challenge_id,
+        language: request.language,
+        code: request.code,
+        author: request.author,
+    };
+    let reward_address = request.reward_address.unwrap_or_else(|| state.wallet.address());
+    match state.jobs.submit(solution, reward_address) {
+        Ok(job_id) => (StatusCode::ACCEPTED, Json(serde_json::json!({ "job_id": job_id }))),
+        Err(err) => (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": err }))),
+    }
+}
+
+// Reports a job's progress and, once it has finished, its verification report.
+async fn get_job(State(state): State<ApiState>, Path(job_id): Path<JobId>) -> (StatusCode, Json<serde_json::Value>) {
+    let Some(record) = state.jobs.get(job_id) else {
+        return not_found(format!("no job #{}", job_id));
+    };
+    let report = record.report.as_ref().map(|report| serde_json::json!({
+        "verdict": format!("{:?}", report.verdict),
+        "passed": report.passed(),
+        "exit_code": report.exit_code,
+        "stdout": report.stdout,
+        "stderr": report.stderr,
+        "build_ms": report.build_duration.map(|duration| duration.as_millis() as u64),
+        "run_ms": report.run_duration.map(|duration| duration.as_millis() as u64),
+        "image_digest": report.image_digest,
+        "mismatch": report.mismatch,
+    }));
+    let block = record.block.as_ref().map(|(index, hash)| serde_json::json!({ "index": index, "hash": hash }));
+    let similar_to = record.similar_to.as_ref().map(|found| {
+        let block = match &found.source {
+            MatchSource::Block { index, hash, author } => serde_json::json!({ "index": index, "hash": hash, "author": author }),
+            MatchSource::Reference => serde_json::Value::Null,
+        };
+        serde_json::json!({ "score": found.score, "block": block, "description": found.to_string() })
+    });
+    // `error` explains a passing solution that was not mined, or one that
+    // was not verified because the chain moved on to another challenge.
+    (StatusCode::OK, Json(serde_json::json!({
+        "job_id": record.id,
+        "challenge_id": record.challenge_id,
+        "language": record.language,
+        "author": record.author,
+        "status": record.status.as_str(),
+        "report": report,
+        "block": block,
+        "error": record.error,
+        "similar_to": similar_to,
+    })))
+}
+
+#[derive(Deserialize)]
+struct AttestationRequest {
+    solution: ChallengeSolution,
+    /// The block the solution is to be mined on; it seeds the generated cases.
+    parent_hash: String,
+}
+
+// Verifies a solution for another node and signs the verdict, pass or not.
+// Only answers to the challenge selected for `parent_hash` are verified.
+async fn attest_solution(
+    State(state): State<ApiState>,
+    Json(request): Json<AttestationRequest>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let parent_index = state.app.lock().unwrap().blockchain.find_header(&request.parent_hash).map(|header| header.index);
+    let Some(parent_index) = parent_index else {
+        let reason = format!("block {} is not known to this node", request.parent_hash);
+        return (StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::json!({ "error": reason })));
+    };
+    let challenge = ChallengeRegistry::global().select(parent_index + 1, &request.parent_hash);
+    if challenge.id != request.solution.challenge_id {
+        let reason = format!("the block after {} answers {}, not {}", request.parent_hash, challenge.id, request.solution.challenge_id);
+        return (StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::json!({ "error": reason })));
+    }
+    if let Err(reason) = state.oracle.check_enabled() {
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({ "error": reason })));
+    }
+    println!("[VERIFIERS] Attestation requested for a {} solution to {}.", request.solution.language, request.solution.challenge_id);
+    let oracle = state.oracle.clone();
+    let solution = request.solution.clone();
+    let parent_hash = request.parent_hash.clone();
+    let verified = tokio::task::spawn_blocking(move || oracle.verify(challenge, &solution, &parent_hash)).await;
+    let report = match verified {
+        Ok(report) => report,
+        Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, Json(serde_json::json!({ "error": err.to_string() }))),
+    };
+    let attestation = state.wallet.attest(&request.solution, &request.parent_hash, &report);
+    (StatusCode::OK, Json(serde_json::json!({ "verdict": format!("{:?}", report.verdict), "attestation": attestation })))
+}
+
+// The challenge the next block has to answer, as shown to solvers.
+async fn get_current_challenge(State(state): State<ApiState>) -> Json<serde_json::Value> {
+    let (index, hash) = {
+        let state_lock = state.app.lock().unwrap();
+        let tip = state_lock.blockchain.last_block();
+        (tip.header.index, tip.hash.clone())
+    };
+    let challenge = ChallengeRegistry::global().select(index + 1, &hash);
+    let starters: serde_json::Map<String, serde_json::Value> = challenge.languages.iter()
+        .map(|(language, entry)| (language.clone(), serde_json::Value::String(entry.starter.clone())))
+        .collect();
     Json(serde_json::json!({
-        "chain": state_lock.blockchain.chain
+        "id": challenge.id,
+        "version": challenge.version,
+        "title": challenge.title,
+        "description": challenge.description,
+        "difficulty": challenge.difficulty,
+        "reward_xp": challenge.difficulty.reward_xp(),
+        "reward_dvc": block_reward(challenge.difficulty).to_string(),
+        "signature": challenge.tests.signature.to_string(),
+        "samples": challenge.tests.samples,
+        "starter_code": starters,
+        "parent": { "index": index, "hash": hash },
     }))
 }
 
-pub async fn start_api_server(app_state: Arc<Mutex<AppState>>) {
+pub async fn start_api_server(api_state: ApiState) {
     let app = Router::new()
         .route("/api/chain", get(get_chain_data))
-        .with_state(app_state)
+        .route("/api/blocks", post(submit_block))
+        .route("/api/blocks/:index", get(get_block))
+        .route("/api/blocks/:index/proof/solution", get(get_solution_proof))
+        .route("/api/blocks/:index/proof/tx/:position", get(get_transaction_proof))
+        .route("/api/transactions", post(submit_transaction))
+        .route("/api/balance/:address", get(get_balance))
+        .route("/api/jobs", post(submit_job))
+        .route("/api/jobs/:id", get(get_job))
+        .route("/api/attestations", post(attest_solution))
+        .route("/api/challenges/current", get(get_current_challenge))
+        .with_state(api_state)
         // This CORS layer allows the browser to make requests
         .layer(CorsLayer::new().allow_origin(Any).allow_methods(Any));
 
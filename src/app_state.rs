@@ -1,10 +1,9 @@
-use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
-use crate::core_types::blockchain::Blockchain;
+use crate::core_types::blockchain::{Block, Blockchain, Reorg};
+use crate::challenges::all_challenges::reward_xp_for;
 use crate::gamification::profile::DeveloperProfile;
 use crate::gamification::guilds::Guild;
 
-#[derive(Serialize, Deserialize)]
 pub struct AppState {
     pub blockchain: Blockchain,
     pub profiles: HashMap<String, DeveloperProfile>,
@@ -19,4 +18,25 @@ impl AppState {
             guilds: HashMap::new(),
         }
     }
-}
\ No newline at end of file
+
+    /// Awards XP for a block that joined the active chain to its author, if
+    /// the author has a profile on this node.
+    pub fn credit_block(&mut self, block: &Block) {
+        let proof = &block.body.proof;
+        if let (Some(profile), Some(xp)) = (self.profiles.get_mut(&proof.author), reward_xp_for(block)) {
+            profile.credit_block(&block.hash, xp, &proof.language);
+        }
+    }
+
+    /// Moves profile XP along with a chain reorganization.
+    pub fn apply_reorg(&mut self, reorg: &Reorg) {
+        for block in &reorg.disconnected {
+            for profile in self.profiles.values_mut() {
+                profile.revoke_block(&block.hash);
+            }
+        }
+        for block in &reorg.connected {
+            self.credit_block(block);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Serialize, Deserialize}; // <-- Added Deserialize
+use crate::oracle::harness::SampleTests;
 
 #[derive(Serialize, Deserialize, Debug, Clone)] // <-- Added Deserialize
 pub struct ChallengeSolution {
@@ -8,13 +9,71 @@ pub struct ChallengeSolution {
     pub author: String,
 }
 
+/// A challenge's tier. It sets how often the challenge is selected, the
+/// XP its solver earns and the DVC its block pays.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    #[default]
+    Easy,
+    Medium,
+    Hard,
+    Expert,
+}
+
+impl Difficulty {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+            Difficulty::Expert => "expert",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Difficulty> {
+        match name {
+            "easy" => Some(Difficulty::Easy),
+            "medium" => Some(Difficulty::Medium),
+            "hard" => Some(Difficulty::Hard),
+            "expert" => Some(Difficulty::Expert),
+            _ => None,
+        }
+    }
+
+    /// How often challenges of this difficulty come up, relative to the
+    /// others: easy ones most, expert ones least.
+    pub fn selection_weight(self) -> u64 {
+        match self {
+            Difficulty::Easy => 4,
+            Difficulty::Medium => 3,
+            Difficulty::Hard => 2,
+            Difficulty::Expert => 1,
+        }
+    }
+
+    /// XP earned by the author of a solution at this tier.
+    pub fn reward_xp(self) -> u32 {
+        match self {
+            Difficulty::Easy => 150,
+            Difficulty::Medium => 250,
+            Difficulty::Hard => 500,
+            Difficulty::Expert => 800,
+        }
+    }
+}
+
 // ... (rest of the file is unchanged)
 pub trait Challenge {
     fn id(&self) -> &str;
     fn title(&self) -> &str;
     fn description(&self) -> &str;
+    fn difficulty(&self) -> Difficulty;
     fn languages(&self) -> Vec<&str>;
-    fn get_solution_for(&self, lang: &str) -> Option<String>;
-    fn get_test_for(&self, lang: &str) -> Option<String>;
-    fn reward_xp(&self) -> u32;
+    /// Code a solver starts from in `lang`, if the challenge provides it.
+    fn starter_code(&self, lang: &str) -> Option<String>;
+    /// The signature and the cases shown to solvers, if the oracle can
+    /// check this challenge. Hidden cases are kept in the oracle store.
+    fn sample_tests(&self) -> Option<SampleTests>;
+    fn reward_xp(&self) -> u32 { self.difficulty().reward_xp() }
 }
\ No newline at end of file
@@ -0,0 +1,174 @@
+// Challenges defined in JSON files, one per challenge, so a new challenge
+// needs no new code or recompile. The files in `challenges/` ship with the
+// node and are the built-in set; `challenges_dir` in the node config points
+// at a directory to load instead. These files hold only what solvers may
+// see; reference solutions and hidden cases are in the oracle store.
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use crate::challenges::challenge_trait::{Challenge, Difficulty};
+use crate::oracle::harness::{SampleTests, TestSuite};
+use crate::oracle::languages::LanguageRegistry;
+
+const BUILT_IN_CHALLENGES: &[(&str, &str)] = &[
+    ("fibonacci.json", include_str!("../../challenges/fibonacci.json")),
+];
+
+/// What a challenge offers in one language.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LanguageEntry {
+    /// Shown to solvers as the code to start from.
+    pub starter: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ChallengeDefinition {
+    pub id: String,
+    /// Raised whenever the tests or reference solutions change. Blocks
+    /// record the version they answered.
+    pub version: u32,
+    pub title: String,
+    pub description: String,
+    pub difficulty: Difficulty,
+    pub languages: BTreeMap<String, LanguageEntry>,
+    pub tests: SampleTests,
+    /// First block height this entry can be drawn for. Changes to the set
+    /// take effect at a height, so blocks below it are still checked
+    /// against the entries that were in force when they were mined.
+    #[serde(default)]
+    pub active_from: u64,
+    /// First block height this entry is no longer drawn for. `None` while
+    /// the entry is current.
+    #[serde(default)]
+    pub retired_at: Option<u64>,
+}
+
+impl ChallengeDefinition {
+    /// Checks the sample cases, and that every language can be tested: it
+    /// needs a driver in the language registry.
+    fn validate(&self, registry: &LanguageRegistry) -> Result<(), String> {
+        if self.id.trim().is_empty() {
+            return Err("id is empty".to_string());
+        }
+        if self.version == 0 {
+            return Err("version must be at least 1".to_string());
+        }
+        if self.languages.is_empty() {
+            return Err("no languages".to_string());
+        }
+        if self.retired_at.is_some_and(|retired_at| retired_at <= self.active_from) {
+            return Err("retired_at must be above active_from".to_string());
+        }
+        TestSuite::new(&self.tests, Vec::new(), None).validate()?;
+        for language in self.languages.keys() {
+            if !registry.contains(language) {
+                return Err(format!("{} has no test harness in the language registry", language));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether this entry can be drawn for the block at `height`.
+    pub fn is_active_at(&self, height: u64) -> bool {
+        self.active_from <= height && self.retired_at.is_none_or(|retired_at| height < retired_at)
+    }
+
+    fn overlaps(&self, other: &ChallengeDefinition) -> bool {
+        self.retired_at.is_none_or(|end| other.active_from < end) && other.retired_at.is_none_or(|end| self.active_from < end)
+    }
+}
+
+impl Challenge for ChallengeDefinition {
+    fn id(&self) -> &str { &self.id }
+    fn title(&self) -> &str { &self.title }
+    fn description(&self) -> &str { &self.description }
+    fn difficulty(&self) -> Difficulty { self.difficulty }
+    fn languages(&self) -> Vec<&str> { self.languages.keys().map(|s| s.as_str()).collect() }
+    fn starter_code(&self, lang: &str) -> Option<String> { self.languages.get(lang).map(|entry| entry.starter.clone()) }
+    fn sample_tests(&self) -> Option<SampleTests> { Some(self.tests.clone()) }
+}
+
+/// Reads every `*.json` file in `dir`, in name order, or the built-in
+/// challenges when no directory is configured.
+pub fn load_definitions(dir: Option<&str>, registry: &LanguageRegistry) -> Result<Vec<ChallengeDefinition>, String> {
+    let files: Vec<(String, String)> = match dir {
+        Some(dir) => read_json_files(Path::new(dir)).map_err(|err| format!("{}: {}", dir, err))?,
+        None => BUILT_IN_CHALLENGES.iter()
+            .map(|(name, contents)| (format!("built-in {}", name), contents.to_string()))
+            .collect(),
+    };
+
+    let mut definitions: Vec<ChallengeDefinition> = Vec::new();
+    for (name, contents) in files {
+        let definition: ChallengeDefinition = serde_json::from_str(&contents).map_err(|err| format!("{}: {}", name, err))?;
+        definition.validate(registry).map_err(|reason| format!("{}: challenge {}: {}", name, definition.id, reason))?;
+        if definitions.iter().any(|other| other.id == definition.id && other.overlaps(&definition)) {
+            return Err(format!("{}: challenge id {} is used twice for the same heights", name, definition.id));
+        }
+        definitions.push(definition);
+    }
+    if definitions.is_empty() {
+        return Err(format!("no challenge files found in {}", dir.unwrap_or("the built-in set")));
+    }
+    // The set only changes where an entry starts or ends, so checking
+    // those heights covers every height.
+    let changes: BTreeSet<u64> = definitions.iter()
+        .flat_map(|definition| [Some(definition.active_from), definition.retired_at])
+        .flatten()
+        .chain([0])
+        .collect();
+    if let Some(height) = changes.into_iter().find(|height| !definitions.iter().any(|definition| definition.is_active_at(*height))) {
+        return Err(format!("no challenge can be drawn for block #{}", height));
+    }
+    Ok(definitions)
+}
+
+/// Name and contents of every `*.json` file in `dir`, in name order.
+pub fn read_json_files(dir: &Path) -> std::io::Result<Vec<(String, String)>> {
+    let mut paths: Vec<_> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    paths.retain(|path| path.extension().is_some_and(|extension| extension == "json"));
+    paths.sort();
+    paths.into_iter()
+        .map(|path| Ok((path.display().to_string(), fs::read_to_string(&path)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Loads the built-in challenge once per `(version, active_from,
+    /// retired_at)`, each from its own file.
+    fn load_schedule(entries: &[(u32, u64, Option<u64>)]) -> Result<Vec<ChallengeDefinition>, String> {
+        let dir = tempfile::tempdir().unwrap();
+        for (number, (version, active_from, retired_at)) in entries.iter().enumerate() {
+            let mut definition: serde_json::Value = serde_json::from_str(BUILT_IN_CHALLENGES[0].1).unwrap();
+            definition["version"] = (*version).into();
+            definition["active_from"] = (*active_from).into();
+            definition["retired_at"] = serde_json::to_value(retired_at).unwrap();
+            fs::write(dir.path().join(format!("{}.json", number)), definition.to_string()).unwrap();
+        }
+        load_definitions(Some(dir.path().to_str().unwrap()), &LanguageRegistry::load(None).unwrap())
+    }
+
+    #[test]
+    fn a_challenge_can_change_version_at_a_height() {
+        let definitions = load_schedule(&[(1, 0, Some(10)), (2, 10, None)]).unwrap();
+        assert!(definitions[0].is_active_at(9) && !definitions[0].is_active_at(10));
+        assert!(!definitions[1].is_active_at(9) && definitions[1].is_active_at(10));
+    }
+
+    #[test]
+    fn overlapping_entries_and_gaps_are_refused() {
+        assert!(load_schedule(&[(1, 0, None), (2, 10, None)]).unwrap_err().contains("used twice for the same heights"));
+        assert!(load_schedule(&[(1, 0, Some(10)), (2, 9, None)]).unwrap_err().contains("used twice for the same heights"));
+        assert!(load_schedule(&[(1, 0, Some(10))]).unwrap_err().contains("no challenge can be drawn for block #10"));
+        assert!(load_schedule(&[(1, 5, None)]).unwrap_err().contains("no challenge can be drawn for block #0"));
+        assert!(load_schedule(&[(1, 5, Some(5))]).unwrap_err().contains("retired_at must be above active_from"));
+    }
+}
@@ -1,3 +1,6 @@
 pub mod all_challenges;
 pub mod challenge_trait;
-pub mod verifier;
\ No newline at end of file
+pub mod definition;
+pub mod oracle_store;
+pub mod registry;
+pub mod verifier;
@@ -0,0 +1,57 @@
+// What only the verification oracle may see: each challenge's reference
+// solutions, hidden cases and case generator. Challenge files are handed to
+// solvers as they are, so none of this goes in them. It is read at startup
+// from `oracle_store_dir`, or from the sample store in `oracle_store/`, which
+// is kept apart from the challenge files but is public with the repository.
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use crate::challenges::definition::read_json_files;
+use crate::oracle::harness::{Generator, SampleTests, TestCase, TestSuite};
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct OracleEntry {
+    /// The challenge this entry belongs to.
+    pub id: String,
+    /// Has to match the challenge file's version.
+    pub version: u32,
+    /// Reference solution per language.
+    pub solutions: BTreeMap<String, String>,
+    #[serde(default)]
+    pub hidden: Vec<TestCase>,
+    #[serde(default)]
+    pub generator: Option<Generator>,
+}
+
+impl OracleEntry {
+    /// The full suite for a challenge whose public part is `tests`.
+    pub fn suite(&self, tests: &SampleTests) -> TestSuite {
+        TestSuite::new(tests, self.hidden.clone(), self.generator.clone())
+    }
+}
+
+pub struct OracleStore {
+    entries: BTreeMap<String, OracleEntry>,
+}
+
+impl OracleStore {
+    /// Reads every `*.json` file in `dir`, in name order.
+    pub fn load(dir: &str) -> Result<OracleStore, String> {
+        let files = read_json_files(Path::new(dir)).map_err(|err| format!("{}: {}", dir, err))?;
+
+        let mut entries = BTreeMap::new();
+        for (name, contents) in files {
+            let entry: OracleEntry = serde_json::from_str(&contents).map_err(|err| format!("{}: {}", name, err))?;
+            if entries.contains_key(&entry.id) {
+                return Err(format!("{}: challenge id {} is used twice", name, entry.id));
+            }
+            entries.insert(entry.id.clone(), entry);
+        }
+        Ok(OracleStore { entries })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&OracleEntry> {
+        self.entries.get(id)
+    }
+}
@@ -0,0 +1,271 @@
+// Every solo challenge this node knows, and which one the next block has to
+// answer. The choice is drawn from the parent block's hash, weighted by
+// difficulty, so every node with the same challenge set agrees on it and no
+// miner can pick an easier challenge. Entries are active for a range of
+// heights, so the draw for an old block uses the set in force at its height.
+use sha2::{Sha256, Digest};
+use std::sync::OnceLock;
+use crate::challenges::definition::ChallengeDefinition;
+use crate::core_types::blockchain::Block;
+use crate::core_types::encoding::Encoder;
+
+static REGISTRY: OnceLock<ChallengeRegistry> = OnceLock::new();
+
+pub struct ChallengeRegistry {
+    /// Sorted by id, then by the height each entry takes effect, so
+    /// selection does not depend on file names or order.
+    challenges: Vec<ChallengeDefinition>,
+}
+
+impl ChallengeRegistry {
+    pub fn new(mut challenges: Vec<ChallengeDefinition>) -> Self {
+        challenges.sort_by(|a, b| a.id.cmp(&b.id).then(a.active_from.cmp(&b.active_from)));
+        ChallengeRegistry { challenges }
+    }
+
+    /// Makes this the registry returned by `global`. Called once at
+    /// startup, before the chain is loaded.
+    pub fn install(self) {
+        if REGISTRY.set(self).is_err() {
+            panic!("the challenge registry is installed twice");
+        }
+    }
+
+    /// Installs the built-in challenges, unless a test already did.
+    #[cfg(test)]
+    pub fn install_built_in() -> &'static ChallengeRegistry {
+        REGISTRY.get_or_init(|| {
+            let languages = crate::oracle::languages::LanguageRegistry::load(None).unwrap();
+            ChallengeRegistry::new(crate::challenges::definition::load_definitions(None, &languages).unwrap())
+        })
+    }
+
+    pub fn global() -> &'static ChallengeRegistry {
+        REGISTRY.get().expect("the challenge registry is not installed")
+    }
+
+    /// The entries that are not retired, i.e. the ones new blocks can
+    /// still be drawn from.
+    pub fn current(&self) -> impl Iterator<Item = &ChallengeDefinition> {
+        self.challenges.iter().filter(|challenge| challenge.retired_at.is_none())
+    }
+
+    /// The entry for `id` in force for the block at `height`.
+    pub fn get_at(&self, id: &str, height: u64) -> Option<&ChallengeDefinition> {
+        self.challenges.iter().find(|challenge| challenge.id == id && challenge.is_active_at(height))
+    }
+
+    /// The challenge the block at `height`, on top of `previous_hash`, has
+    /// to answer.
+    pub fn select(&self, height: u64, previous_hash: &str) -> &ChallengeDefinition {
+        let active: Vec<&ChallengeDefinition> = self.challenges.iter().filter(|challenge| challenge.is_active_at(height)).collect();
+        let total: u64 = active.iter().map(|challenge| challenge.difficulty.selection_weight()).sum();
+        let digest = Sha256::digest(format!("challenge:{}", previous_hash).as_bytes());
+        let mut ticket = u64::from_be_bytes(digest[..8].try_into().unwrap()) % total;
+        for challenge in active {
+            let weight = challenge.difficulty.selection_weight();
+            if ticket < weight {
+                return challenge;
+            }
+            ticket -= weight;
+        }
+        unreachable!("the ticket is below the total weight")
+    }
+
+    /// SHA-256 over each entry's id, version, difficulty and active heights.
+    /// Nodes only agree on selections when their fingerprints match.
+    pub fn fingerprint(&self) -> String {
+        let mut enc = Encoder::new();
+        for challenge in &self.challenges {
+            enc.str(&challenge.id).u32(challenge.version).str(challenge.difficulty.as_str())
+                .u64(challenge.active_from).u64(challenge.retired_at.unwrap_or(0));
+        }
+        format!("{:x}", Sha256::digest(enc.finish()))
+    }
+
+    /// Checks that a block answers the challenge selected for its parent, in
+    /// the version in force at its height. This is a chain rule, checked for
+    /// every block from any source, so a change to the set has to start at
+    /// a height above the blocks already mined.
+    pub fn check_block(&self, block: &Block) -> Result<(), String> {
+        let expected = self.select(block.header.index, &block.header.previous_hash);
+        let proof = &block.body.proof;
+        if proof.challenge_id != expected.id {
+            return Err(format!("answers {}, but the selected challenge is {}", proof.challenge_id, expected.id));
+        }
+        if block.header.challenge_version != expected.version {
+            return Err(format!(
+                "answers version {} of {}, but version {} is in force at its height",
+                block.header.challenge_version, expected.id, expected.version,
+            ));
+        }
+        if block.header.difficulty != expected.difficulty {
+            return Err(format!(
+                "is mined as {}, but {} is {}",
+                block.header.difficulty.as_str(), expected.id, expected.difficulty.as_str(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+    use crate::challenges::challenge_trait::{ChallengeSolution, Difficulty};
+    use crate::challenges::definition::load_definitions;
+    use crate::core_types::blockchain::BlockBody;
+    use crate::oracle::languages::LanguageRegistry;
+
+    /// The built-in challenge under another id and tier.
+    fn definition(id: &str, difficulty: Difficulty) -> ChallengeDefinition {
+        let languages = LanguageRegistry::load(None).unwrap();
+        let mut definition = load_definitions(None, &languages).unwrap().remove(0);
+        definition.id = id.to_string();
+        definition.difficulty = difficulty;
+        definition
+    }
+
+    fn four_tiers() -> Vec<ChallengeDefinition> {
+        vec![
+            definition("anagrams", Difficulty::Easy),
+            definition("bracket_matching", Difficulty::Medium),
+            definition("cycle_detection", Difficulty::Hard),
+            definition("dijkstra", Difficulty::Expert),
+        ]
+    }
+
+    fn parent_hash(i: u32) -> String {
+        format!("{:x}", Sha256::digest(i.to_be_bytes()))
+    }
+
+    fn answering(registry: &ChallengeRegistry, previous_hash: &str) -> Block {
+        let challenge = registry.select(1, previous_hash);
+        let proof = ChallengeSolution {
+            challenge_id: challenge.id.clone(),
+            language: "Python".to_string(),
+            code: "def solve(n): return n".to_string(),
+            author: "ada".to_string(),
+        };
+        Block::new(1, 1_700_000_000, previous_hash.to_string(), challenge.version, challenge.difficulty,
+            BlockBody { transactions: vec![], proof, attestations: vec![] })
+    }
+
+    #[test]
+    fn selection_does_not_depend_on_load_order() {
+        let forward = ChallengeRegistry::new(four_tiers());
+        let mut reversed = four_tiers();
+        reversed.reverse();
+        let reversed = ChallengeRegistry::new(reversed);
+        for i in 0..200 {
+            let hash = parent_hash(i);
+            assert_eq!(forward.select(1, &hash).id, reversed.select(1, &hash).id);
+        }
+        assert_eq!(forward.fingerprint(), reversed.fingerprint());
+    }
+
+    #[test]
+    fn selection_matches_golden_vector() {
+        let registry = ChallengeRegistry::new(four_tiers());
+        let selected: Vec<&str> = (0..8).map(|i| registry.select(1, &parent_hash(i)).id.as_str()).collect();
+        assert_eq!(selected, [
+            "anagrams", "bracket_matching", "anagrams", "anagrams",
+            "anagrams", "bracket_matching", "cycle_detection", "cycle_detection",
+        ]);
+    }
+
+    #[test]
+    fn selection_is_weighted_by_difficulty() {
+        let registry = ChallengeRegistry::new(four_tiers());
+        let mut counts = [0u32; 4];
+        for i in 0..10_000 {
+            let id = &registry.select(1, &parent_hash(i)).id;
+            counts[registry.current().position(|challenge| &challenge.id == id).unwrap()] += 1;
+        }
+        // Weights 4:3:2:1 out of 10.
+        for (count, expected) in counts.iter().zip([4_000, 3_000, 2_000, 1_000]) {
+            assert!(count.abs_diff(expected) < 250, "{:?}", counts);
+        }
+    }
+
+    #[test]
+    fn check_block_requires_the_selected_challenge_version_and_tier() {
+        let registry = ChallengeRegistry::new(four_tiers());
+        let hash = parent_hash(0);
+        let block = answering(&registry, &hash);
+        assert_eq!(registry.check_block(&block), Ok(()));
+
+        let mut other = block.clone();
+        other.body.proof.challenge_id = registry.current()
+            .find(|challenge| challenge.id != block.body.proof.challenge_id).unwrap().id.clone();
+        assert!(registry.check_block(&other).unwrap_err().contains("selected challenge"));
+
+        let mut old_version = block.clone();
+        old_version.header.challenge_version += 1;
+        assert!(registry.check_block(&old_version).unwrap_err().contains("in force at its height"));
+
+        let mut easier = block;
+        easier.header.difficulty = if easier.header.difficulty == Difficulty::Easy { Difficulty::Medium } else { Difficulty::Easy };
+        assert!(registry.check_block(&easier).unwrap_err().contains("is mined as"));
+    }
+
+    #[test]
+    fn fingerprint_changes_with_a_version_bump() {
+        let before = ChallengeRegistry::new(four_tiers()).fingerprint();
+        let mut bumped = four_tiers();
+        bumped[2].version += 1;
+        assert_ne!(ChallengeRegistry::new(bumped).fingerprint(), before);
+    }
+
+    /// `count` blocks on top of one another, each answering the challenge
+    /// `registry` draws for it.
+    fn chain(registry: &ChallengeRegistry, count: u64) -> Vec<Block> {
+        let mut blocks: Vec<Block> = Vec::new();
+        for height in 1..=count {
+            let previous_hash = blocks.last().map_or("00".repeat(32), |block| block.hash.clone());
+            let mut block = answering(registry, &previous_hash);
+            block.header.index = height;
+            block.hash = block.header.calculate_hash();
+            blocks.push(block);
+        }
+        blocks
+    }
+
+    #[test]
+    fn old_blocks_replay_against_the_set_in_force_at_their_height() {
+        let before = ChallengeRegistry::new(four_tiers());
+        let blocks = chain(&before, 40);
+
+        // From height 41 on: dijkstra is dropped, anagrams becomes medium,
+        // and a new challenge joins.
+        let mut changed = four_tiers();
+        for definition in changed.iter_mut().filter(|definition| ["anagrams", "dijkstra"].contains(&definition.id.as_str())) {
+            definition.retired_at = Some(41);
+        }
+        let mut reweighted = definition("anagrams", Difficulty::Medium);
+        reweighted.version = 2;
+        reweighted.active_from = 41;
+        let mut added = definition("edit_distance", Difficulty::Hard);
+        added.active_from = 41;
+        changed.extend([reweighted, added]);
+        let after = ChallengeRegistry::new(changed);
+
+        for block in &blocks {
+            assert_eq!(after.check_block(block), Ok(()), "block #{}", block.header.index);
+        }
+        assert_ne!(after.fingerprint(), before.fingerprint());
+        assert_eq!(after.get_at("anagrams", 40).unwrap().version, 1);
+        assert_eq!(after.get_at("anagrams", 41).unwrap().version, 2);
+        assert!(after.get_at("dijkstra", 41).is_none());
+        let drawn: BTreeSet<&str> = (0..200).map(|i| after.select(41, &parent_hash(i)).id.as_str()).collect();
+        assert_eq!(drawn, BTreeSet::from(["anagrams", "bracket_matching", "cycle_detection", "edit_distance"]));
+
+        // The same change applied to every height breaks the old blocks.
+        let mut unscheduled = four_tiers();
+        unscheduled.retain(|definition| definition.id != "dijkstra");
+        unscheduled.push(definition("edit_distance", Difficulty::Hard));
+        let unscheduled = ChallengeRegistry::new(unscheduled);
+        assert!(blocks.iter().any(|block| unscheduled.check_block(block).is_err()));
+    }
+}
@@ -1,18 +1,207 @@
+use crate::challenges::registry::ChallengeRegistry;
 use crate::challenges::challenge_trait::{Challenge, ChallengeSolution};
-use crate::oracle::docker_verifier::DockerOracle;
+use crate::challenges::oracle_store::{OracleEntry, OracleStore};
+use crate::core_types::blockchain::Block;
+use crate::oracle::harness::{Generator, TestCase, TestSuite};
+use crate::oracle::report::{Verdict, VerificationReport};
+use crate::oracle::runner::OracleRunner;
 
-pub struct VerificationOracle;
+const VERIFICATION_DISABLED: &str = "verification is disabled: this node has no oracle store (set oracle_store_dir)";
+
+pub struct VerificationOracle {
+    runner: OracleRunner,
+    /// `None` when no oracle store is configured; nothing can be verified.
+    store: Option<OracleStore>,
+}
+
+/// What running a mined block's solution again showed.
+pub enum Reverification {
+    /// Nothing to run: the genesis block, or a challenge without a test suite.
+    Skipped(String),
+    /// Still passes. `same_report` is false when the report no longer
+    /// matches any attested one, e.g. a different toolchain image.
+    Passed { report: VerificationReport, same_report: bool },
+    /// Fails now although it was mined as a pass.
+    Drifted(VerificationReport),
+    /// The sandbox failed, so the block could not be checked.
+    Inconclusive(VerificationReport),
+}
 
 impl VerificationOracle {
-    pub fn verify(challenge: &dyn Challenge, solution: &ChallengeSolution) -> bool {
-        println!("[VERIFIER] Handing off to Docker Oracle for secure execution...");
+    pub fn new(runner: OracleRunner, store: Option<OracleStore>) -> Self {
+        VerificationOracle { runner, store }
+    }
+
+    /// Fails when this node has no oracle store to verify solutions with.
+    pub fn check_enabled(&self) -> Result<(), String> {
+        match self.store {
+            Some(_) => Ok(()),
+            None => Err(VERIFICATION_DISABLED.to_string()),
+        }
+    }
+
+    pub fn backend_name(&self) -> &str {
+        self.runner.backend_name()
+    }
+
+    /// The signature a solution to `challenge` must have in `language`.
+    pub fn signature_for(&self, challenge: &dyn Challenge, language: &str) -> Option<String> {
+        challenge.sample_tests().and_then(|tests| self.runner.describe(language, &tests.signature))
+    }
 
-        if let Some(test_code) = challenge.get_test_for(&solution.language) {
-            DockerOracle::verify(&solution.language, &solution.code, &test_code)
+    /// The oracle store's reference solution to `challenge_id` in `language`.
+    pub fn reference_solution(&self, challenge_id: &str, language: &str) -> Option<&str> {
+        self.store.as_ref()?.get(challenge_id)?.solutions.get(language).map(|code| code.as_str())
+    }
+
+    /// Checks that the store has an entry for every challenge in `registry`,
+    /// written for the same version, and that the composed suites can run.
+    /// There is nothing to check without a store.
+    pub fn check_challenges(&self, registry: &ChallengeRegistry) -> Result<(), String> {
+        let Some(store) = &self.store else { return Ok(()) };
+        for challenge in registry.current() {
+            let entry = store.get(&challenge.id)
+                .ok_or_else(|| format!("challenge {} has no oracle store entry", challenge.id))?;
+            if entry.version != challenge.version {
+                return Err(format!(
+                    "challenge {} is at version {}, but its oracle store entry is for version {}",
+                    challenge.id, challenge.version, entry.version,
+                ));
+            }
+            entry.suite(&challenge.tests).validate().map_err(|reason| format!("challenge {}: {}", challenge.id, reason))?;
+            for language in entry.solutions.keys() {
+                if !challenge.languages.contains_key(language) {
+                    return Err(format!("challenge {} has a {} reference solution, but not that language", challenge.id, language));
+                }
+            }
+            if let Some(generator) = &entry.generator
+                && !entry.solutions.contains_key(&generator.reference_language) {
+                return Err(format!("challenge {}: the generator needs a {} reference solution", challenge.id, generator.reference_language));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks `solution` against the challenge's sample cases and the hidden
+    /// cases in the oracle store.
+    /// Generated cases are drawn from `seed`, the hash of the block the
+    /// solution will be mined on, so they cannot be known in advance.
+    pub fn verify(&self, challenge: &dyn Challenge, solution: &ChallengeSolution, seed: &str) -> VerificationReport {
+        println!("[VERIFIER] Handing off to the {} sandbox for execution...", self.runner.backend_name());
+
+        if let Some(tests) = challenge.sample_tests() {
+            let Some(store) = &self.store else {
+                return VerificationReport::infra_error(VERIFICATION_DISABLED);
+            };
+            let Some(entry) = store.get(challenge.id()) else {
+                return VerificationReport::infra_error(format!("challenge {} has no oracle store entry", challenge.id()));
+            };
+            let mut suite = entry.suite(&tests);
+            if let Err(reason) = suite.validate() {
+                return VerificationReport::infra_error(format!("invalid test suite: {}", reason));
+            }
+            if let Some(generator) = suite.generator.take() {
+                match self.generated_cases(challenge, entry, &suite, &generator, seed) {
+                    Ok(cases) => suite.hidden.extend(cases),
+                    Err(reason) => return VerificationReport::infra_error(reason),
+                }
+            }
+            self.runner.verify(&solution.language, &solution.code, &suite)
         } else {
-            println!("[VERIFIER-WARN] No test cases found for language: {}. Assuming success for this simplified challenge.", solution.language);
-            // For simplified raid challenges, we'll just check if the solution is not empty
-            !solution.code.trim().is_empty()
+            // Nothing to check the solution against, so it cannot pass.
+            VerificationReport::infra_error(format!("challenge {} has no test cases", challenge.id()))
+        }
+    }
+
+    /// Runs the solution stored in `block` again, on the same cases it was
+    /// verified against when it was mined.
+    pub fn reverify(&self, block: &Block) -> Reverification {
+        if block.header.index == 0 {
+            return Reverification::Skipped("genesis block".to_string());
         }
+        if let Err(reason) = self.check_enabled() {
+            return Reverification::Skipped(reason);
+        }
+        let solution = &block.body.proof;
+        let Some(challenge) = ChallengeRegistry::global().get_at(&solution.challenge_id, block.header.index) else {
+            return Reverification::Skipped(format!("challenge {} is not run by the oracle", solution.challenge_id));
+        };
+        // Blocks from before challenge versions were recorded carry 0.
+        if block.header.challenge_version != 0 && block.header.challenge_version != challenge.version {
+            return Reverification::Skipped(format!(
+                "it answered version {} of {}, and this node has version {}",
+                block.header.challenge_version, challenge.id, challenge.version,
+            ));
+        }
+        let report = self.verify(challenge, solution, &block.header.previous_hash);
+        match report.verdict {
+            Verdict::Passed => {
+                let digest = report.digest();
+                let same_report = block.body.attestations.iter().any(|attestation| attestation.report_hash == digest);
+                Reverification::Passed { report, same_report }
+            }
+            Verdict::InfraError => Reverification::Inconclusive(report),
+            _ => Reverification::Drifted(report),
+        }
+    }
+
+    fn generated_cases(&self, challenge: &dyn Challenge, entry: &OracleEntry, suite: &TestSuite, generator: &Generator, seed: &str) -> Result<Vec<TestCase>, String> {
+        let language = &generator.reference_language;
+        let reference = entry.solutions.get(language)
+            .ok_or_else(|| format!("challenge {} has no {} reference solution", challenge.id(), language))?;
+        let inputs = generator.generate(&format!("{}:{}", seed, challenge.id()));
+        println!("[VERIFIER] Answering {} generated cases with the {} reference solution...", inputs.len(), language);
+        self.runner.answer(language, reference, suite, inputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oracle::languages::LanguageRegistry;
+    use crate::oracle::limits::LimitsConfig;
+    use crate::oracle::sandbox::LocalProcessBackend;
+
+    // Python solutions run on the host through the local-process backend.
+    fn oracle(store: Option<OracleStore>) -> VerificationOracle {
+        let runner = OracleRunner::new(Box::new(LocalProcessBackend), LimitsConfig::default(), LanguageRegistry::load(None).unwrap());
+        VerificationOracle::new(runner, store)
+    }
+
+    fn shipped_store() -> OracleStore {
+        OracleStore::load(concat!(env!("CARGO_MANIFEST_DIR"), "/oracle_store")).unwrap()
+    }
+
+    fn python(code: &str) -> ChallengeSolution {
+        ChallengeSolution {
+            challenge_id: "fibonacci".to_string(),
+            language: "Python".to_string(),
+            code: code.to_string(),
+            author: "ada".to_string(),
+        }
+    }
+
+    #[test]
+    fn shipped_store_loads_and_verifies_a_known_good_solution() {
+        let registry = ChallengeRegistry::install_built_in();
+        let oracle = oracle(Some(shipped_store()));
+        assert_eq!(oracle.check_challenges(registry), Ok(()));
+        let challenge = registry.get_at("fibonacci", 1).unwrap();
+
+        let reference = oracle.reference_solution("fibonacci", "Python").unwrap().to_string();
+        let report = oracle.verify(challenge, &python(&reference), "00ab");
+        assert_eq!(report.verdict, Verdict::Passed, "{}", report.stderr);
+
+        let report = oracle.verify(challenge, &python("def solve(n):\n    return n\n"), "00ab");
+        assert_eq!(report.verdict, Verdict::WrongAnswer);
+    }
+
+    #[test]
+    fn verification_is_disabled_without_a_store() {
+        let registry = ChallengeRegistry::install_built_in();
+        let oracle = oracle(None);
+        assert_eq!(oracle.check_enabled(), Err(VERIFICATION_DISABLED.to_string()));
+        let report = oracle.verify(registry.get_at("fibonacci", 1).unwrap(), &python("def solve(n):\n    return n\n"), "00ab");
+        assert_eq!(report.verdict, Verdict::InfraError);
     }
-}
\ No newline at end of file
+}
@@ -1,49 +1,69 @@
 use std::io::{self, Write};
 use std::sync::{Arc, Mutex};
-use crate::p2p::P2PService;
-use crate::challenges::all_challenges::{FibonacciChallenge, WebAppRaidChallenge};
+use std::time::Duration;
+use crate::challenges::registry::ChallengeRegistry;
 use crate::challenges::challenge_trait::{Challenge, ChallengeSolution};
-use crate::challenges::verifier::VerificationOracle;
+use crate::challenges::verifier::{Reverification, VerificationOracle};
+use crate::oracle::report::{Verdict, VerificationReport};
 use crate::vm::executor::Executor;
 use crate::app_state::AppState;
-use crate::core_types::blockchain::Blockchain;
-use crate::gamification::profile::DeveloperProfile;
 use crate::gamification::guilds::Guild;
-use std::collections::HashMap;
+use crate::core_types::amount::Amount;
+use crate::core_types::blockchain::{block_reward, Block};
+use crate::storage::state_file;
+use crate::jobs::{JobQueue, JobStatus};
+use crate::wallet::Wallet;
 
 // This is the main loop for the command-line interface.
-pub async fn run_cli(state: Arc<Mutex<AppState>>, dev_id: &str, p2p: &P2PService) {
+pub async fn run_cli(state: Arc<Mutex<AppState>>, dev_id: &str, wallet: &Wallet, oracle: &VerificationOracle, jobs: &JobQueue) {
     loop {
         println!("\n--- DevChain CLI ---");
         println!("  1. View Blockchain");
         println!("  2. Mine New Block (Solo Challenge)");
-        println!("  3. Tackle Raid Challenge (Team)");
-        println!("  4. Execute from Solution Ledger");
-        println!("  5. Manage Guild");
-        println!("  6. View My Profile");
-        println!("  7. Exit");
+        println!("  3. Execute from Solution Ledger");
+        println!("  4. Manage Guild");
+        println!("  5. View My Profile");
+        println!("  6. Send DVC");
+        println!("  7. Check Balance");
+        println!("  8. Exit");
+        println!("  Or re-run mined solutions: verify-block <index> | verify-chain [--from <index>]");
         print!("> ");
         io::stdout().flush().unwrap();
 
         let mut choice = String::new();
         io::stdin().read_line(&mut choice).expect("Failed to read line");
 
+        if choice.trim() == "2" {
+            mine_solo_block(&state, dev_id, wallet, oracle, jobs).await;
+            continue;
+        }
+        if let Some(range) = parse_reverify_command(choice.trim()) {
+            match range {
+                Ok((from, to)) => reverify_blocks(&state, oracle, from, to),
+                Err(usage) => println!("[ERROR] {}", usage),
+            }
+            continue;
+        }
+
         // Lock the state once per loop iteration.
         let mut state_lock = state.lock().unwrap();
 
         match choice.trim() {
             "1" => view_blockchain(&state_lock),
-            "2" => mine_solo_block(&mut state_lock, dev_id, p2p).await,
-            "3" => tackle_raid_challenge(&mut state_lock, dev_id, p2p).await,
-            "4" => execute_from_ledger(&state_lock),
-            "5" => manage_guild(&mut state_lock, dev_id),
-            "6" => display_profile(&state_lock, dev_id),
-            "7" | "exit" => {
+            "3" => execute_from_ledger(&state_lock),
+            "4" => manage_guild(&mut state_lock, dev_id),
+            "5" => display_profile(&state_lock, dev_id),
+            "6" => send_dvc(&mut state_lock, wallet),
+            "7" => check_balance(&state_lock, wallet),
+            "8" | "exit" => {
                 println!("Exiting DevChain node...");
                 break;
             }
             _ => println!("[ERROR] Invalid choice."),
         }
+        // Blocks are already in the block store; this keeps profiles, guilds
+        // and queued transactions from being lost if the node is killed.
+        state_file::save(&state_lock);
     }
 }
 
@@ -55,9 +75,11 @@ fn display_profile(state: &AppState, dev_id: &str) {
 }
 
 fn view_blockchain(state: &AppState) {
-    println!("\n--- Full DevChain ---");
+    // Headers only; use the Solution Ledger to see a block's code.
+    println!("\n--- DevChain Headers ---");
     for block in &state.blockchain.chain {
-        println!("{}", serde_json::to_string_pretty(block).unwrap());
+        println!("Block #{} {}", block.header.index, block.hash);
+        println!("{}", serde_json::to_string_pretty(&block.header).unwrap());
         println!("---------------------");
     }
 }
@@ -76,10 +98,19 @@ fn get_solution_from_user(lang_choice: &str) -> String {
     solution_code
 }
 
-async fn mine_solo_block(state: &mut AppState, dev_id: &str, p2p: &P2PService) {
-    let challenge = FibonacciChallenge::new();
-    println!("\n--- New Mining Challenge ---");
+// The next block's challenge is drawn from the tip's hash. Verification
+// happens on the job queue's workers, so the node state is not locked while
+// we wait for the verdict.
+async fn mine_solo_block(state: &Mutex<AppState>, dev_id: &str, wallet: &Wallet, oracle: &VerificationOracle, jobs: &JobQueue) {
+    let (next_index, tip_hash) = {
+        let state = state.lock().unwrap();
+        let tip = state.blockchain.last_block();
+        (tip.header.index + 1, tip.hash.clone())
+    };
+    let challenge = ChallengeRegistry::global().select(next_index, &tip_hash);
+    println!("\n--- New Mining Challenge for Block #{} ---", next_index);
     println!("Title: {}", challenge.title());
+    println!("Difficulty: {:?} ({} XP, {} DVC)", challenge.difficulty(), challenge.reward_xp(), block_reward(challenge.difficulty()));
     println!("Description: {}", challenge.description());
     println!("Supported Languages: {:?}", challenge.languages());
     
@@ -93,67 +124,157 @@ async fn mine_solo_block(state: &mut AppState, dev_id: &str, p2p: &P2PService) {
         println!("[ERROR] Language not supported for this challenge.");
         return;
     }
+    if let Some(signature) = oracle.signature_for(challenge, lang_choice) {
+        println!("Signature: {}", signature);
+        println!("Only the answer may go to stdout; print debugging output to stderr.");
+    }
+    if let Some(tests) = challenge.sample_tests() {
+        println!("Sample cases:");
+        for case in &tests.samples {
+            println!("  solve({}) => {}", case.input.join(", "), case.expected);
+        }
+        println!("The oracle may also run your solution on cases that are not shown.");
+    }
+    if let Some(starter) = challenge.starter_code(lang_choice) {
+        println!("Starter code:\n{}", starter.trim_end());
+    }
 
     let solution_code = get_solution_from_user(lang_choice);
-    let developer = state.profiles.get(dev_id).unwrap(); // Immutable borrow first
     let solution = ChallengeSolution {
         challenge_id: challenge.id().to_string(),
         language: lang_choice.to_string(),
         code: solution_code,
-        author: developer.developer_id.clone(),
+        author: dev_id.to_string(),
+    };
+
+    let job_id = match jobs.submit(solution, wallet.address()) {
+        Ok(job_id) => job_id,
+        Err(err) => {
+            println!("[ERROR] Could not queue the solution: {}", err);
+            return;
+        }
+    };
+    println!("[INFO] Solution queued for verification as job #{}. Waiting for the result...", job_id);
+    let record = loop {
+        match jobs.get(job_id) {
+            Some(record) if record.status == JobStatus::Finished => break record,
+            Some(_) => tokio::time::sleep(Duration::from_millis(200)).await,
+            None => {
+                println!("[ERROR] Job #{} is no longer known to the queue.", job_id);
+                return;
+            }
+        }
     };
 
-    if VerificationOracle::verify(&challenge, &solution) {
-        println!("[SUCCESS] Solution correct! Mining new block...");
-        // Get mutable borrow only when needed
-        let developer_mut = state.profiles.get_mut(dev_id).unwrap();
-        developer_mut.add_xp(challenge.reward_xp(), &solution.language);
-        
-        let new_block = state.blockchain.mine_new_block(solution, &developer_mut.developer_id);
-        println!("\n[!] Block #{} successfully mined!", new_block.index);
-        println!("[!] Hash: {}", new_block.hash);
-        p2p.broadcast_block(&new_block.hash);
+    if let Some(found) = &record.similar_to {
+        println!("[WARN] Flagged as a possible copy: the solution is {}.", found);
+    }
+    let Some(report) = record.report else { return };
+    print_report(&report);
+    if let Some((index, _)) = record.block {
+        println!("[SUCCESS] Solution correct! It was mined into block #{}.", index);
+    } else if let Some(reason) = record.error {
+        println!("[ERROR] Solution correct, but it could not be mined: {}", reason);
+    } else if report.passed() {
+        println!("[SUCCESS] Solution correct!");
+    } else if report.verdict == Verdict::InfraError {
+        println!("[ERROR] The oracle could not check your solution; this does not count against it. Please try again.");
     } else {
         println!("[FAILURE] Solution incorrect. Please try again.");
     }
 }
 
-async fn tackle_raid_challenge(state: &mut AppState, dev_id: &str, p2p: &P2PService) {
-    let developer = state.profiles.get(dev_id).unwrap();
-    if developer.guild_id.is_none() {
-        println!("[ERROR] You must be in a guild to tackle a raid challenge.");
-        return;
+fn print_report(report: &VerificationReport) {
+    println!("\n--- Verification Report ---");
+    println!("Verdict: {:?} ({})", report.verdict, report.verdict);
+    if let Some(code) = report.exit_code {
+        println!("Exit code: {}", code);
     }
-    
-    let raid = WebAppRaidChallenge::new();
-    println!("\n--- New RAID Challenge ---");
-    println!("A Web App needs to be built! It requires a frontend and a backend.");
-    
-    let frontend_challenge = raid.sub_challenges.get("frontend").unwrap();
-    println!("\nSolving: {}", frontend_challenge.title());
-    println!("Description: {}", frontend_challenge.description());
-    let frontend_solution_code = get_solution_from_user("JavaScript");
-    
-    let backend_challenge = raid.sub_challenges.get("backend").unwrap();
-    println!("\nSolving: {}", backend_challenge.title());
-    println!("Description: {}", backend_challenge.description());
-    let backend_solution_code = get_solution_from_user("Python");
-
-    let combined_code = format!("--FRONTEND--\n{}\n--BACKEND--\n{}", frontend_solution_code, backend_solution_code);
-    let raid_solution = ChallengeSolution {
-        challenge_id: "webapp_raid".to_string(),
-        language: "WebApp (JS/Python)".to_string(),
-        code: combined_code,
-        author: developer.guild_id.as_deref().unwrap_or(&developer.developer_id).to_string(),
+    if let Some(duration) = report.build_duration {
+        println!("Build time: {:.2}s", duration.as_secs_f64());
+    }
+    if let Some(duration) = report.run_duration {
+        println!("Run time: {:.2}s", duration.as_secs_f64());
+    }
+    if let Some(digest) = &report.image_digest {
+        println!("Image: {}", digest);
+    }
+    if let Some(mismatch) = &report.mismatch {
+        println!("Failed: {}", mismatch);
+    }
+    // Solution output was already streamed above; sandbox failures were not.
+    if report.verdict == Verdict::InfraError {
+        println!("Reason: {}", report.stderr.trim_end());
+    }
+}
+
+/// Parses `verify-block <index>` and `verify-chain [--from <index>]` into an
+/// inclusive block range; `None` for any other input.
+fn parse_reverify_command(input: &str) -> Option<Result<(u64, Option<u64>), String>> {
+    let mut words = input.split_whitespace();
+    let command = words.next()?;
+    let args: Vec<&str> = words.collect();
+    let parsed = match (command, args.as_slice()) {
+        ("verify-block", [index]) => index.parse().ok().map(|index| (index, Some(index))),
+        ("verify-block", _) => None,
+        ("verify-chain", []) => Some((1, None)),
+        ("verify-chain", ["--from", index]) => index.parse().ok().map(|index| (index, None)),
+        ("verify-chain", _) => None,
+        _ => return None,
     };
+    Some(parsed.ok_or_else(|| "Usage: verify-block <index> | verify-chain [--from <index>]".to_string()))
+}
 
-    println!("\n[RAID] All parts submitted! Verifying and mining...");
-    let developer_mut = state.profiles.get_mut(dev_id).unwrap();
-    developer_mut.add_xp(500, "Raid");
-    
-    let new_block = state.blockchain.mine_new_block(raid_solution, developer_mut.guild_id.as_deref().unwrap_or(&developer_mut.developer_id));
-    println!("\n[!] RAID COMPLETE! Block #{} successfully mined!", new_block.index);
-    p2p.broadcast_block(&new_block.hash);
+// Runs the solutions of the given blocks through the oracle again and
+// reports any that no longer pass. The blocks are copied out first so the
+// node state is not locked while the oracle runs.
+fn reverify_blocks(state: &Mutex<AppState>, oracle: &VerificationOracle, from: u64, to: Option<u64>) {
+    let blocks: Vec<Block> = {
+        let state = state.lock().unwrap();
+        state.blockchain.chain.iter()
+            .filter(|block| block.header.index >= from && to.is_none_or(|to| block.header.index <= to))
+            .cloned()
+            .collect()
+    };
+    if blocks.is_empty() {
+        println!("[ERROR] No blocks from #{} on the current chain.", from);
+        return;
+    }
+
+    let (mut passed, mut drifted, mut skipped, mut inconclusive) = (0, 0, 0, 0);
+    for block in &blocks {
+        let solution = &block.body.proof;
+        println!("\n--- Re-verifying Block #{}: {} in {} by {} ---", block.header.index, solution.challenge_id, solution.language, solution.author);
+        match oracle.reverify(block) {
+            Reverification::Skipped(reason) => {
+                skipped += 1;
+                println!("[REVERIFY] Block #{} skipped: {}.", block.header.index, reason);
+            }
+            Reverification::Passed { report, same_report } => {
+                passed += 1;
+                if same_report {
+                    println!("[REVERIFY] Block #{} still passes.", block.header.index);
+                } else {
+                    print_report(&report);
+                    println!("[REVERIFY] Block #{} still passes, but its report differs from the attested one (toolchain or image changed?).", block.header.index);
+                }
+            }
+            Reverification::Drifted(report) => {
+                drifted += 1;
+                print_report(&report);
+                println!("[DRIFT] Block #{} no longer passes: {}.", block.header.index, report.verdict);
+            }
+            Reverification::Inconclusive(report) => {
+                inconclusive += 1;
+                print_report(&report);
+                println!("[WARN] Block #{} could not be checked; the sandbox failed.", block.header.index);
+            }
+        }
+    }
+    println!(
+        "\n[REVERIFY] {} block(s): {} still pass, {} drifted, {} skipped, {} could not be checked.",
+        blocks.len(), passed, drifted, skipped, inconclusive,
+    );
 }
 
 fn execute_from_ledger(state: &AppState) {
@@ -167,8 +288,8 @@ fn execute_from_ledger(state: &AppState) {
 
 fn manage_guild(state: &mut AppState, dev_id: &str) {
     let developer = state.profiles.get_mut(dev_id).unwrap();
-    if developer.guild_id.is_some() {
-        println!("You are already in guild: {}", developer.guild_id.as_ref().unwrap());
+    if let Some(guild_id) = &developer.guild_id {
+        println!("You are already in guild: {}", guild_id);
         return;
     }
     
@@ -183,4 +304,46 @@ fn manage_guild(state: &mut AppState, dev_id: &str) {
     state.guilds.insert(guild_id.clone(), new_guild);
     developer.guild_id = Some(guild_id);
     println!("Guild '{}' created successfully!", guild_name);
-}
\ No newline at end of file
+}
+
+fn send_dvc(state: &mut AppState, wallet: &Wallet) {
+    print!("Recipient address: ");
+    io::stdout().flush().unwrap();
+    let mut recipient = String::new();
+    io::stdin().read_line(&mut recipient).expect("Failed to read line");
+    let recipient = recipient.trim();
+
+    print!("Amount (DVC): ");
+    io::stdout().flush().unwrap();
+    let mut amount_str = String::new();
+    io::stdin().read_line(&mut amount_str).expect("Failed to read line");
+    let amount = match amount_str.parse::<Amount>() {
+        Ok(amount) => amount,
+        Err(err) => {
+            println!("[ERROR] '{}' is not a valid amount: {}.", amount_str.trim(), err);
+            return;
+        }
+    };
+
+    let nonce = state.blockchain.pending_accounts().next_nonce(&wallet.address());
+    let tx = wallet.transfer(recipient, amount, nonce);
+    match state.blockchain.add_transaction(tx) {
+        Ok(()) => println!("[SUCCESS] Transfer of {} DVC to {} queued for the next block.", amount, recipient),
+        Err(err) => println!("[ERROR] Transfer rejected: {}", err),
+    }
+}
+
+fn check_balance(state: &AppState, wallet: &Wallet) {
+    print!("Address (leave empty for your wallet): ");
+    io::stdout().flush().unwrap();
+    let mut address = String::new();
+    io::stdin().read_line(&mut address).expect("Failed to read line");
+    let address = match address.trim() {
+        "" => wallet.address(),
+        other => other.to_string(),
+    };
+
+    println!("\n--- Balance: {} ---", address);
+    println!("  Confirmed: {} DVC", state.blockchain.confirmed_accounts().balance(&address));
+    println!("  Including pending: {} DVC", state.blockchain.pending_accounts().balance(&address));
+}
@@ -0,0 +1,61 @@
+// Node settings read from `devchain_config.json`. Every field has a default,
+// so the file is optional.
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use crate::oracle::limits::LimitsConfig;
+use crate::oracle::sandbox::SandboxKind;
+use crate::similarity::SimilarityConfig;
+use crate::verifiers::VerifierConfig;
+
+pub const CONFIG_PATH: &str = "devchain_config.json";
+/// Where the oracle store is looked for when `oracle_store_dir` is not set.
+pub const DEFAULT_ORACLE_STORE_DIR: &str = "oracle_store";
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct NodeConfig {
+    /// Which sandbox the verification oracle runs solutions in.
+    pub sandbox: SandboxKind,
+    /// Time, memory, CPU and process limits for solutions, per language.
+    pub limits: LimitsConfig,
+    /// Path of a language registry file to use instead of the built-in one.
+    pub language_registry: Option<String>,
+    /// Directory of challenge files to use instead of the built-in ones.
+    pub challenges_dir: Option<String>,
+    /// Directory of oracle store files. Without it `DEFAULT_ORACLE_STORE_DIR`
+    /// is used if it exists; with neither, verification is disabled.
+    pub oracle_store_dir: Option<String>,
+    /// How many solutions are verified at the same time.
+    pub verification_workers: usize,
+    /// Whose attestations count, and how many a block needs.
+    pub verifiers: VerifierConfig,
+    /// When a submission counts as a copy of an earlier solution, and what
+    /// happens to it.
+    pub similarity: SimilarityConfig,
+}
+
+impl Default for NodeConfig {
+    fn default() -> Self {
+        NodeConfig {
+            sandbox: SandboxKind::default(),
+            limits: LimitsConfig::default(),
+            language_registry: None,
+            challenges_dir: None,
+            oracle_store_dir: None,
+            verification_workers: 2,
+            verifiers: VerifierConfig::default(),
+            similarity: SimilarityConfig::default(),
+        }
+    }
+}
+
+impl NodeConfig {
+    pub fn load() -> Result<NodeConfig, String> {
+        match fs::read_to_string(CONFIG_PATH) {
+            Ok(contents) => serde_json::from_str(&contents).map_err(|err| err.to_string()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(NodeConfig::default()),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use crate::core_types::amount::Amount;
+use crate::core_types::blockchain::{Block, SYSTEM_SENDER};
+use crate::core_types::transaction::{Transaction, TransactionError};
+
+/// Balances and last-used nonces, rebuilt by replaying blocks in order.
+#[derive(Debug, Default, Clone)]
+pub struct AccountState {
+    balances: HashMap<String, Amount>,
+    nonces: HashMap<String, u64>,
+}
+
+impl AccountState {
+    pub fn from_blocks<'a>(blocks: impl IntoIterator<Item = &'a Block>) -> Self {
+        let mut state = AccountState::default();
+        for block in blocks {
+            for tx in &block.body.transactions {
+                // Blocks handed to this function have already been validated.
+                let _ = state.apply(tx);
+            }
+        }
+        state
+    }
+
+    pub fn balance(&self, address: &str) -> Amount {
+        self.balances.get(address).copied().unwrap_or(Amount::ZERO)
+    }
+
+    /// The nonce the next transaction from `address` must carry.
+    pub fn next_nonce(&self, address: &str) -> u64 {
+        self.nonces.get(address).copied().unwrap_or(0) + 1
+    }
+
+    /// Moves funds for one transaction. Rewards from the system sender mint new
+    /// coins; everything else must come from a sender that can cover it.
+    pub fn apply(&mut self, tx: &Transaction) -> Result<(), TransactionError> {
+        if tx.amount.is_zero() {
+            return Err(TransactionError::InvalidAmount);
+        }
+        if tx.sender != SYSTEM_SENDER {
+            let expected = self.next_nonce(&tx.sender);
+            if tx.nonce != expected {
+                return Err(TransactionError::UnexpectedNonce { expected });
+            }
+            let available = self.balance(&tx.sender);
+            let remaining = available.checked_sub(tx.amount)
+                .ok_or(TransactionError::InsufficientFunds { available })?;
+            self.balances.insert(tx.sender.clone(), remaining);
+            self.nonces.insert(tx.sender.clone(), tx.nonce);
+        }
+        let credited = self.balance(&tx.recipient).checked_add(tx.amount)
+            .ok_or(TransactionError::BalanceOverflow)?;
+        self.balances.insert(tx.recipient.clone(), credited);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transfer(sender: &str, recipient: &str, dvc: u64, nonce: u64) -> Transaction {
+        Transaction {
+            sender: sender.to_string(),
+            recipient: recipient.to_string(),
+            amount: Amount::from_dvc(dvc),
+            public_key: String::new(),
+            nonce,
+            signature: String::new(),
+        }
+    }
+
+    fn funded(address: &str, dvc: u64) -> AccountState {
+        let mut state = AccountState::default();
+        state.apply(&transfer(SYSTEM_SENDER, address, dvc, 0)).unwrap();
+        state
+    }
+
+    #[test]
+    fn transfers_move_balances_and_advance_the_nonce() {
+        let mut state = funded("alice", 10);
+        assert_eq!(state.next_nonce("alice"), 1);
+
+        state.apply(&transfer("alice", "bob", 3, 1)).unwrap();
+        state.apply(&transfer("alice", "bob", 2, 2)).unwrap();
+        assert_eq!(state.balance("alice"), Amount::from_dvc(5));
+        assert_eq!(state.balance("bob"), Amount::from_dvc(5));
+        assert_eq!(state.next_nonce("alice"), 3);
+        // Receiving does not use up a nonce.
+        assert_eq!(state.next_nonce("bob"), 1);
+        assert_eq!(state.balance("carol"), Amount::ZERO);
+    }
+
+    #[test]
+    fn out_of_order_nonces_are_refused() {
+        let mut state = funded("alice", 10);
+        assert_eq!(state.apply(&transfer("alice", "bob", 1, 2)), Err(TransactionError::UnexpectedNonce { expected: 1 }));
+        state.apply(&transfer("alice", "bob", 1, 1)).unwrap();
+        // Replaying the same transfer is refused too.
+        assert_eq!(state.apply(&transfer("alice", "bob", 1, 1)), Err(TransactionError::UnexpectedNonce { expected: 2 }));
+        assert_eq!(state.balance("alice"), Amount::from_dvc(9));
+    }
+
+    #[test]
+    fn overdrafts_are_refused_without_touching_balances() {
+        let mut state = funded("alice", 10);
+        assert_eq!(
+            state.apply(&transfer("alice", "bob", 11, 1)),
+            Err(TransactionError::InsufficientFunds { available: Amount::from_dvc(10) }),
+        );
+        assert_eq!(state.balance("alice"), Amount::from_dvc(10));
+        assert_eq!(state.balance("bob"), Amount::ZERO);
+        assert_eq!(state.next_nonce("alice"), 1);
+
+        // Spending the whole balance is allowed.
+        state.apply(&transfer("alice", "bob", 10, 1)).unwrap();
+        assert_eq!(state.balance("alice"), Amount::ZERO);
+        assert_eq!(state.apply(&transfer("bob", "alice", 0, 1)), Err(TransactionError::InvalidAmount));
+    }
+}
@@ -0,0 +1,171 @@
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Number of base units in one DevCoin.
+pub const UNITS_PER_DVC: u64 = 100_000_000;
+const DECIMALS: usize = 8;
+
+/// A DevCoin quantity, stored as an integer number of base units so that
+/// every node adds, compares and hashes it exactly the same way.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[serde(transparent)]
+pub struct Amount(u64);
+
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+
+    pub const fn from_units(units: u64) -> Self {
+        Amount(units)
+    }
+
+    pub const fn from_dvc(dvc: u64) -> Self {
+        Amount(dvc * UNITS_PER_DVC)
+    }
+
+    pub fn units(self) -> u64 {
+        self.0
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn checked_add(self, other: Amount) -> Option<Amount> {
+        self.0.checked_add(other.0).map(Amount)
+    }
+
+    pub fn checked_sub(self, other: Amount) -> Option<Amount> {
+        self.0.checked_sub(other.0).map(Amount)
+    }
+}
+
+/// Formats as DVC with up to eight decimals and no trailing zeros, e.g. `100` or `0.5`.
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let whole = self.0 / UNITS_PER_DVC;
+        let fraction = self.0 % UNITS_PER_DVC;
+        if fraction == 0 {
+            write!(f, "{}", whole)
+        } else {
+            let digits = format!("{:0width$}", fraction, width = DECIMALS);
+            write!(f, "{}.{}", whole, digits.trim_end_matches('0'))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseAmountError {
+    Empty,
+    InvalidDigit,
+    TooManyDecimals,
+    Overflow,
+}
+
+impl fmt::Display for ParseAmountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseAmountError::Empty => write!(f, "amount is empty"),
+            ParseAmountError::InvalidDigit => write!(f, "amount must be a non-negative decimal number"),
+            ParseAmountError::TooManyDecimals => write!(f, "amount has more than {} decimal places", DECIMALS),
+            ParseAmountError::Overflow => write!(f, "amount is too large"),
+        }
+    }
+}
+
+impl std::error::Error for ParseAmountError {}
+
+/// Parses a decimal DVC amount such as `12`, `0.5` or `3.00000001`.
+impl FromStr for Amount {
+    type Err = ParseAmountError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(ParseAmountError::Empty);
+        }
+        let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+        if whole.is_empty() && fraction.is_empty() {
+            return Err(ParseAmountError::InvalidDigit);
+        }
+        if !whole.bytes().chain(fraction.bytes()).all(|b| b.is_ascii_digit()) {
+            return Err(ParseAmountError::InvalidDigit);
+        }
+        if fraction.len() > DECIMALS {
+            return Err(ParseAmountError::TooManyDecimals);
+        }
+
+        let whole_units = if whole.is_empty() {
+            0
+        } else {
+            whole.parse::<u64>().map_err(|_| ParseAmountError::Overflow)?
+                .checked_mul(UNITS_PER_DVC).ok_or(ParseAmountError::Overflow)?
+        };
+        let fraction_units = if fraction.is_empty() {
+            0
+        } else {
+            format!("{:0<width$}", fraction, width = DECIMALS).parse::<u64>().map_err(|_| ParseAmountError::InvalidDigit)?
+        };
+        whole_units.checked_add(fraction_units).map(Amount).ok_or(ParseAmountError::Overflow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Amount, ParseAmountError> {
+        text.parse()
+    }
+
+    #[test]
+    fn parses_whole_and_fractional_dvc() {
+        assert_eq!(parse("12"), Ok(Amount::from_dvc(12)));
+        assert_eq!(parse(" 0.5 "), Ok(Amount::from_units(50_000_000)));
+        assert_eq!(parse(".25"), Ok(Amount::from_units(25_000_000)));
+        assert_eq!(parse("3."), Ok(Amount::from_dvc(3)));
+        assert_eq!(parse("3.00000001"), Ok(Amount::from_units(300_000_001)));
+        assert_eq!(parse("0"), Ok(Amount::ZERO));
+    }
+
+    #[test]
+    fn refuses_malformed_amounts() {
+        assert_eq!(parse(""), Err(ParseAmountError::Empty));
+        assert_eq!(parse("."), Err(ParseAmountError::InvalidDigit));
+        assert_eq!(parse("-1"), Err(ParseAmountError::InvalidDigit));
+        assert_eq!(parse("1.2.3"), Err(ParseAmountError::InvalidDigit));
+        assert_eq!(parse("1e3"), Err(ParseAmountError::InvalidDigit));
+        assert_eq!(parse("0.000000001"), Err(ParseAmountError::TooManyDecimals));
+        assert_eq!(parse("1.123456789"), Err(ParseAmountError::TooManyDecimals));
+    }
+
+    #[test]
+    fn largest_amount_parses_and_one_more_unit_overflows() {
+        let largest = Amount::from_units(u64::MAX);
+        assert_eq!(largest.to_string(), "184467440737.09551615");
+        assert_eq!(parse("184467440737.09551615"), Ok(largest));
+        assert_eq!(parse("184467440737.09551616"), Err(ParseAmountError::Overflow));
+        assert_eq!(parse("184467440738"), Err(ParseAmountError::Overflow));
+        assert_eq!(parse("99999999999999999999999"), Err(ParseAmountError::Overflow));
+    }
+
+    #[test]
+    fn displays_without_trailing_zeros() {
+        assert_eq!(Amount::from_dvc(100).to_string(), "100");
+        assert_eq!(Amount::from_units(50_000_000).to_string(), "0.5");
+        assert_eq!(Amount::from_units(1).to_string(), "0.00000001");
+        assert_eq!(Amount::ZERO.to_string(), "0");
+        for text in ["7", "0.1", "2.30000001"] {
+            assert_eq!(parse(text).unwrap().to_string(), text);
+        }
+    }
+
+    #[test]
+    fn checked_arithmetic_stops_at_the_bounds() {
+        let one = Amount::from_units(1);
+        assert_eq!(Amount::from_dvc(2).checked_add(Amount::from_dvc(3)), Some(Amount::from_dvc(5)));
+        assert_eq!(Amount::from_units(u64::MAX).checked_add(one), None);
+        assert_eq!(Amount::from_dvc(5).checked_sub(Amount::from_dvc(5)), Some(Amount::ZERO));
+        assert_eq!(Amount::ZERO.checked_sub(one), None);
+    }
+}
@@ -0,0 +1,188 @@
+// A verifier's signed statement that it ran a solution through its oracle
+// and what came out. Blocks carry the attestations for their solution, so
+// other nodes can check who verified it instead of running it again.
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use libp2p::identity;
+use crate::core_types::encoding::{Canonical, DecodeError, Decoder, Encoder};
+use crate::core_types::hex;
+use crate::core_types::transaction::Transaction;
+
+/// Domain tag mixed into every signed attestation, so it can never be
+/// mistaken for a transaction signature or vice versa.
+const SIGNING_DOMAIN: &[u8] = b"devchain-attestation-v1";
+
+/// The verdict of an attestation that vouches for a solution.
+pub const PASSED_VERDICT: &str = "Passed";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Attestation {
+    /// `BlockBody::solution_hash` of the verified solution.
+    pub solution_hash: String,
+    pub challenge_id: String,
+    /// The block the solution was verified on top of. Its hash seeds the
+    /// generated test cases, so a verdict only holds for that parent.
+    pub parent_hash: String,
+    pub verdict: String,
+    /// SHA-256 of the verifier's report, see `VerificationReport::digest`.
+    pub report_hash: String,
+    pub public_key: String,
+    pub signature: String,
+}
+
+/// Why an attestation does not count for a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttestationError {
+    InvalidPublicKey,
+    InvalidSignature,
+    NotPassed,
+    WrongSolution,
+    WrongParent,
+}
+
+impl fmt::Display for AttestationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttestationError::InvalidPublicKey => write!(f, "public key is not a valid Ed25519 key"),
+            AttestationError::InvalidSignature => write!(f, "signature does not verify"),
+            AttestationError::NotPassed => write!(f, "verdict is not a pass"),
+            AttestationError::WrongSolution => write!(f, "attests a different solution"),
+            AttestationError::WrongParent => write!(f, "was verified on a different parent block"),
+        }
+    }
+}
+
+impl std::error::Error for AttestationError {}
+
+impl Attestation {
+    pub fn new_signed(
+        keypair: &identity::ed25519::Keypair,
+        solution_hash: &str,
+        challenge_id: &str,
+        parent_hash: &str,
+        verdict: &str,
+        report_hash: &str,
+    ) -> Self {
+        let mut attestation = Attestation {
+            solution_hash: solution_hash.to_string(),
+            challenge_id: challenge_id.to_string(),
+            parent_hash: parent_hash.to_string(),
+            verdict: verdict.to_string(),
+            report_hash: report_hash.to_string(),
+            public_key: hex::encode(&keypair.public().to_bytes()),
+            signature: String::new(),
+        };
+        attestation.signature = hex::encode(&keypair.sign(&attestation.signing_bytes()));
+        attestation
+    }
+
+    /// Canonical bytes covered by the signature: the domain tag followed by
+    /// every field except the signature itself.
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        let mut enc = Encoder::new();
+        enc.bytes(SIGNING_DOMAIN)
+            .str(&self.solution_hash)
+            .str(&self.challenge_id)
+            .str(&self.parent_hash)
+            .str(&self.verdict)
+            .str(&self.report_hash)
+            .str(&self.public_key);
+        enc.finish()
+    }
+
+    /// Checks the signature and returns the verifier's address.
+    pub fn verifier(&self) -> Result<String, AttestationError> {
+        let key_bytes = hex::decode(&self.public_key).ok_or(AttestationError::InvalidPublicKey)?;
+        let public_key = identity::ed25519::PublicKey::try_from_bytes(&key_bytes)
+            .map_err(|_| AttestationError::InvalidPublicKey)?;
+        let signature = hex::decode(&self.signature).ok_or(AttestationError::InvalidSignature)?;
+        if !public_key.verify(&self.signing_bytes(), &signature) {
+            return Err(AttestationError::InvalidSignature);
+        }
+        Ok(Transaction::address_for(&public_key))
+    }
+
+    /// Checks that this is a signed pass for the given solution and parent,
+    /// and returns the verifier's address.
+    pub fn check(&self, solution_hash: &str, challenge_id: &str, parent_hash: &str) -> Result<String, AttestationError> {
+        if self.solution_hash != solution_hash || self.challenge_id != challenge_id {
+            return Err(AttestationError::WrongSolution);
+        }
+        if self.parent_hash != parent_hash {
+            return Err(AttestationError::WrongParent);
+        }
+        if self.verdict != PASSED_VERDICT {
+            return Err(AttestationError::NotPassed);
+        }
+        self.verifier()
+    }
+}
+
+impl Canonical for Attestation {
+    fn encode(&self, enc: &mut Encoder) {
+        enc.str(&self.solution_hash)
+            .str(&self.challenge_id)
+            .str(&self.parent_hash)
+            .str(&self.verdict)
+            .str(&self.report_hash)
+            .str(&self.public_key)
+            .str(&self.signature);
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, DecodeError> {
+        Ok(Attestation {
+            solution_hash: dec.string()?,
+            challenge_id: dec.string()?,
+            parent_hash: dec.string()?,
+            verdict: dec.string()?,
+            report_hash: dec.string()?,
+            public_key: dec.string()?,
+            signature: dec.string()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOLUTION: &str = "5f1e";
+    const PARENT: &str = "00ab";
+
+    fn signed(keypair: &identity::ed25519::Keypair, verdict: &str) -> Attestation {
+        Attestation::new_signed(keypair, SOLUTION, "fibonacci", PARENT, verdict, "feed")
+    }
+
+    #[test]
+    fn pass_for_the_same_solution_and_parent_names_the_verifier() {
+        let keypair = identity::ed25519::Keypair::generate();
+        let attestation = signed(&keypair, PASSED_VERDICT);
+        assert_eq!(attestation.check(SOLUTION, "fibonacci", PARENT), Ok(Transaction::address_for(&keypair.public())));
+    }
+
+    #[test]
+    fn tampered_fields_break_the_signature() {
+        let keypair = identity::ed25519::Keypair::generate();
+        let mut attestation = signed(&keypair, PASSED_VERDICT);
+        attestation.report_hash = "beef".to_string();
+        assert_eq!(attestation.check(SOLUTION, "fibonacci", PARENT), Err(AttestationError::InvalidSignature));
+
+        let mut foreign_key = signed(&keypair, PASSED_VERDICT);
+        foreign_key.public_key = hex::encode(&identity::ed25519::Keypair::generate().public().to_bytes());
+        assert_eq!(foreign_key.check(SOLUTION, "fibonacci", PARENT), Err(AttestationError::InvalidSignature));
+
+        let mut garbage_key = signed(&keypair, PASSED_VERDICT);
+        garbage_key.public_key = "zz".to_string();
+        assert_eq!(garbage_key.check(SOLUTION, "fibonacci", PARENT), Err(AttestationError::InvalidPublicKey));
+    }
+
+    #[test]
+    fn attestation_only_holds_for_its_solution_parent_and_a_pass() {
+        let keypair = identity::ed25519::Keypair::generate();
+        let attestation = signed(&keypair, PASSED_VERDICT);
+        assert_eq!(attestation.check(SOLUTION, "fibonacci", "00ac"), Err(AttestationError::WrongParent));
+        assert_eq!(attestation.check("5f1f", "fibonacci", PARENT), Err(AttestationError::WrongSolution));
+        assert_eq!(attestation.check(SOLUTION, "primes", PARENT), Err(AttestationError::WrongSolution));
+        assert_eq!(signed(&keypair, "WrongAnswer").check(SOLUTION, "fibonacci", PARENT), Err(AttestationError::NotPassed));
+    }
+}
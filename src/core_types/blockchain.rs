@@ -1,38 +1,339 @@
 use serde::{Serialize, Deserialize}; // <-- Added Deserialize
 use sha2::{Sha256, Digest};
+use std::collections::HashMap;
+use std::fmt;
 use std::time::{SystemTime, UNIX_EPOCH};
-use crate::core_types::transaction::Transaction;
-use crate::challenges::challenge_trait::ChallengeSolution;
+use crate::core_types::accounts::AccountState;
+use crate::core_types::amount::Amount;
+use crate::core_types::attestation::{Attestation, AttestationError};
+use crate::core_types::encoding::{Canonical, DecodeError, Decoder, Encoder};
+use crate::core_types::hex;
+use crate::core_types::merkle::{self, MerkleProof};
+use crate::core_types::transaction::{Transaction, TransactionError};
+use crate::challenges::challenge_trait::{ChallengeSolution, Difficulty};
+use crate::challenges::registry::ChallengeRegistry;
+use crate::storage::block_store::{BlockStore, StoreError};
 
-#[derive(Serialize, Deserialize, Debug, Clone)] // <-- Added Deserialize
-pub struct Block {
-    // ... (rest of the struct is unchanged)
+/// Version of the block layout, and the first field of its canonical encoding.
+pub const BLOCK_VERSION: u32 = 5;
+/// Oldest layout still accepted. Version 2 blocks carry no attestations,
+/// version 3 blocks no challenge version and version 4 blocks no difficulty;
+/// all are kept so older chains still load.
+pub const MIN_BLOCK_VERSION: u32 = 2;
+
+/// Sender address used for block reward transactions.
+pub const SYSTEM_SENDER: &str = "0";
+/// DevCoin (DVC) paid to the miner of an easy block, and of every block
+/// before version 5.
+pub const BLOCK_REWARD: Amount = Amount::from_dvc(100);
+/// How far ahead of the local clock a block timestamp may be, in seconds.
+const MAX_FUTURE_DRIFT_SECS: u64 = 2 * 60 * 60;
+
+/// The fixed-size part of a block. The block hash covers only the header,
+/// which commits to the body through `tx_root`, `solution_hash` and
+/// `attestation_root`, so headers can be listed, synced and linked without
+/// the code payloads.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BlockHeader {
+    pub version: u32,
     pub index: u64,
     pub timestamp: u64,
+    pub previous_hash: String,
+    /// Merkle root over the block's transactions.
+    pub tx_root: String,
+    /// SHA-256 of the canonical encoding of the block's solution.
+    pub solution_hash: String,
+    /// SHA-256 of the block's attestations. Empty before version 3.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub attestation_root: String,
+    /// Version of the challenge definition the solution answered; the
+    /// challenge itself is named in the solution. 0 before version 4.
+    #[serde(default)]
+    pub challenge_version: u32,
+    /// Tier of the challenge, which sets the block reward. Easy before
+    /// version 5, since those blocks all paid the easy reward.
+    #[serde(default)]
+    pub difficulty: Difficulty,
+}
+
+impl BlockHeader {
+    pub fn calculate_hash(&self) -> String {
+        format!("{:x}", Sha256::digest(self.to_canonical_bytes()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BlockBody {
     pub transactions: Vec<Transaction>,
     pub proof: ChallengeSolution,
-    pub previous_hash: String,
+    /// Verifiers' signed passes for `proof`.
+    #[serde(default)]
+    pub attestations: Vec<Attestation>,
+}
+
+impl BlockBody {
+    fn tx_leaves(&self) -> Vec<merkle::Hash> {
+        self.transactions.iter()
+            .map(|tx| merkle::leaf_hash(&tx.to_canonical_bytes()))
+            .collect()
+    }
+
+    pub fn tx_root(&self) -> String {
+        hex::encode(&merkle::root(&self.tx_leaves()))
+    }
+
+    pub fn solution_hash(&self) -> String {
+        self.proof.hash()
+    }
+
+    pub fn attestation_root(&self) -> String {
+        let mut enc = Encoder::new();
+        enc.seq(&self.attestations);
+        format!("{:x}", Sha256::digest(enc.finish()))
+    }
+
+    fn encode(&self, version: u32, enc: &mut Encoder) {
+        enc.seq(&self.transactions);
+        self.proof.encode(enc);
+        if version >= 3 {
+            enc.seq(&self.attestations);
+        }
+    }
+
+    fn decode(version: u32, dec: &mut Decoder) -> Result<Self, DecodeError> {
+        Ok(BlockBody {
+            transactions: dec.seq()?,
+            proof: ChallengeSolution::decode(dec)?,
+            attestations: if version >= 3 { dec.seq()? } else { Vec::new() },
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)] // <-- Added Deserialize
+pub struct Block {
+    pub header: BlockHeader,
+    pub body: BlockBody,
     pub hash: String,
 }
 
-// ... (impl Block is unchanged)
 impl Block {
-    pub fn calculate_hash(&self) -> String {
-        let mut block_data = self.clone();
-        block_data.hash = String::new();
-        let serialized = serde_json::to_string(&block_data).expect("Failed to serialize block");
-        
-        let mut hasher = Sha256::new();
-        hasher.update(serialized.as_bytes());
-        format!("{:x}", hasher.finalize())
+    /// Builds a block whose header commits to `body`, and hashes it.
+    pub fn new(index: u64, timestamp: u64, previous_hash: String, challenge_version: u32, difficulty: Difficulty, body: BlockBody) -> Self {
+        Self::with_version(BLOCK_VERSION, index, timestamp, previous_hash, challenge_version, difficulty, body)
+    }
+
+    /// Builds a `MIN_BLOCK_VERSION` block, for chains from before blocks
+    /// recorded their challenge, which are not held to the selection.
+    pub fn legacy(index: u64, timestamp: u64, previous_hash: String, body: BlockBody) -> Self {
+        Self::with_version(MIN_BLOCK_VERSION, index, timestamp, previous_hash, 0, Difficulty::Easy, body)
+    }
+
+    fn with_version(version: u32, index: u64, timestamp: u64, previous_hash: String, challenge_version: u32, difficulty: Difficulty, body: BlockBody) -> Self {
+        let attestation_root = if version >= 3 { body.attestation_root() } else { String::new() };
+        let header = BlockHeader {
+            version,
+            index,
+            timestamp,
+            previous_hash,
+            tx_root: body.tx_root(),
+            solution_hash: body.solution_hash(),
+            attestation_root,
+            challenge_version,
+            difficulty,
+        };
+        let hash = header.calculate_hash();
+        Block { header, body, hash }
+    }
+
+    /// Inclusion proof for the transaction at `position` against `header.tx_root`.
+    pub fn prove_transaction(&self, position: usize) -> Option<MerkleProof> {
+        MerkleProof::build(&self.body.tx_leaves(), position)
     }
 }
 
+impl Canonical for BlockHeader {
+    fn encode(&self, enc: &mut Encoder) {
+        enc.u32(self.version)
+            .u64(self.index)
+            .u64(self.timestamp)
+            .str(&self.previous_hash)
+            .str(&self.tx_root)
+            .str(&self.solution_hash);
+        if self.version >= 3 {
+            enc.str(&self.attestation_root);
+        }
+        if self.version >= 4 {
+            enc.u32(self.challenge_version);
+        }
+        if self.version >= 5 {
+            enc.str(self.difficulty.as_str());
+        }
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, DecodeError> {
+        let version = dec.u32()?;
+        if !(MIN_BLOCK_VERSION..=BLOCK_VERSION).contains(&version) {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        Ok(BlockHeader {
+            version,
+            index: dec.u64()?,
+            timestamp: dec.u64()?,
+            previous_hash: dec.string()?,
+            tx_root: dec.string()?,
+            solution_hash: dec.string()?,
+            attestation_root: if version >= 3 { dec.string()? } else { String::new() },
+            challenge_version: if version >= 4 { dec.u32()? } else { 0 },
+            difficulty: if version >= 5 {
+                Difficulty::parse(&dec.string()?).ok_or(DecodeError::InvalidField("difficulty"))?
+            } else {
+                Difficulty::Easy
+            },
+        })
+    }
+}
+
+/// Wire form of a block: header then body, in the header's layout version.
+/// The hash is recomputed on decode.
+impl Canonical for Block {
+    fn encode(&self, enc: &mut Encoder) {
+        self.header.encode(enc);
+        self.body.encode(self.header.version, enc);
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, DecodeError> {
+        let header = BlockHeader::decode(dec)?;
+        let body = BlockBody::decode(header.version, dec)?;
+        let hash = header.calculate_hash();
+        Ok(Block { header, body, hash })
+    }
+}
+
+impl ChallengeSolution {
+    /// SHA-256 of the canonical encoding, as committed to by `solution_hash`.
+    pub fn hash(&self) -> String {
+        format!("{:x}", Sha256::digest(self.to_canonical_bytes()))
+    }
+}
+
+impl Canonical for ChallengeSolution {
+    fn encode(&self, enc: &mut Encoder) {
+        enc.str(&self.challenge_id)
+            .str(&self.language)
+            .str(&self.code)
+            .str(&self.author);
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, DecodeError> {
+        Ok(ChallengeSolution {
+            challenge_id: dec.string()?,
+            language: dec.string()?,
+            code: dec.string()?,
+            author: dec.string()?,
+        })
+    }
+}
+
+/// The first rule a chain breaks, tagged with the index of the offending block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainError {
+    EmptyChain,
+    InvalidGenesis { reason: &'static str },
+    UnsupportedVersion { index: u64, version: u32 },
+    IndexMismatch { index: u64, expected: u64 },
+    DuplicateBlock { index: u64 },
+    UnknownParent { index: u64 },
+    TxRootMismatch { index: u64 },
+    SolutionHashMismatch { index: u64 },
+    AttestationRootMismatch { index: u64 },
+    InvalidAttestation { index: u64, position: usize, reason: AttestationError },
+    VersionDowngrade { index: u64 },
+    HashMismatch { index: u64 },
+    BrokenLink { index: u64 },
+    TimestampBeforeParent { index: u64 },
+    TimestampInFuture { index: u64 },
+    InvalidReward { index: u64, reason: &'static str },
+    /// The block does not answer the challenge selected for its parent.
+    WrongChallenge { index: u64, reason: String },
+    InvalidTransaction { index: u64, position: usize, reason: TransactionError },
+    /// The block could not be written to the block store, so it was not taken.
+    StoreFailed { index: u64, reason: String },
+}
+
+impl fmt::Display for ChainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainError::EmptyChain => write!(f, "chain has no genesis block"),
+            ChainError::InvalidGenesis { reason } => write!(f, "block #0: invalid genesis block ({})", reason),
+            ChainError::UnsupportedVersion { index, version } => write!(f, "block #{}: unsupported block version {}", index, version),
+            ChainError::IndexMismatch { index, expected } => write!(f, "block #{}: expected index {}", index, expected),
+            ChainError::DuplicateBlock { index } => write!(f, "block #{}: block is already known", index),
+            ChainError::UnknownParent { index } => write!(f, "block #{}: parent block is unknown", index),
+            ChainError::TxRootMismatch { index } => write!(f, "block #{}: tx_root does not match the block's transactions", index),
+            ChainError::SolutionHashMismatch { index } => write!(f, "block #{}: solution_hash does not match the block's solution", index),
+            ChainError::AttestationRootMismatch { index } => write!(f, "block #{}: attestation_root does not match the block's attestations", index),
+            ChainError::InvalidAttestation { index, position, reason } => write!(f, "block #{}: attestation {} is invalid ({})", index, position, reason),
+            ChainError::VersionDowngrade { index } => write!(f, "block #{}: block version is older than its parent's", index),
+            ChainError::HashMismatch { index } => write!(f, "block #{}: stored hash does not match block contents", index),
+            ChainError::BrokenLink { index } => write!(f, "block #{}: previous_hash does not match the parent block", index),
+            ChainError::TimestampBeforeParent { index } => write!(f, "block #{}: timestamp is earlier than its parent", index),
+            ChainError::TimestampInFuture { index } => write!(f, "block #{}: timestamp is too far in the future", index),
+            ChainError::InvalidReward { index, reason } => write!(f, "block #{}: invalid block reward ({})", index, reason),
+            ChainError::WrongChallenge { index, reason } => write!(f, "block #{}: {}", index, reason),
+            ChainError::InvalidTransaction { index, position, reason } => write!(f, "block #{}: transaction {} is invalid ({})", index, position, reason),
+            ChainError::StoreFailed { index, reason } => write!(f, "block #{}: could not be stored ({})", index, reason),
+        }
+    }
+}
+
+impl std::error::Error for ChainError {}
+
+/// Blocks that left and joined the active chain when a heavier branch won.
+#[derive(Debug)]
+pub struct Reorg {
+    pub disconnected: Vec<Block>,
+    pub connected: Vec<Block>,
+}
+
+/// Where an accepted block ended up.
+#[derive(Debug)]
+pub enum AcceptOutcome {
+    /// It extended the active chain.
+    Extended,
+    /// It was stored on a side branch that is not (yet) heavier than the active chain.
+    SideBranch,
+    /// Its branch became the heaviest, and the active chain was switched over to it.
+    Reorganized(Reorg),
+}
+
+/// DVC paid to the miner of a block at `difficulty`: a multiple of the easy
+/// reward. This is a chain rule, so every node checks the same amount.
+pub fn block_reward(difficulty: Difficulty) -> Amount {
+    let multiple = match difficulty {
+        Difficulty::Easy => 1,
+        Difficulty::Medium => 2,
+        Difficulty::Hard => 3,
+        Difficulty::Expert => 5,
+    };
+    Amount::from_units(BLOCK_REWARD.units() * multiple)
+}
+
+/// Fork-choice weight of a block. Every block counts the same, whatever its
+/// challenge's difficulty, so "heaviest" means "longest": the branch with
+/// more blocks wins, and on a tie the active chain stays.
+pub fn block_weight(_block: &Block) -> u64 {
+    1
+}
 
-#[derive(Serialize, Deserialize)] // <-- Added derive macro
 pub struct Blockchain {
+    /// The active chain, from genesis to the tip chosen by fork choice.
     pub chain: Vec<Block>,
     pub pending_transactions: Vec<Transaction>,
+    /// Valid-looking blocks not on the active chain, keyed by hash. Each one's
+    /// parent is either on the active chain or is itself a side block.
+    pub side_blocks: HashMap<String, Block>,
+    /// Where accepted blocks are written. `None` keeps the chain in memory only.
+    store: Option<BlockStore>,
 }
 
 // ... (impl Blockchain is unchanged)
@@ -41,11 +342,56 @@ impl Blockchain {
         let mut blockchain = Blockchain {
             chain: Vec::new(),
             pending_transactions: Vec::new(),
+            side_blocks: HashMap::new(),
+            store: None,
         };
         blockchain.create_genesis_block();
         blockchain
     }
 
+    /// Rebuilds a chain from blocks in the order they were accepted, genesis
+    /// first, putting every later block through `accept_block` again.
+    pub fn from_blocks(blocks: Vec<Block>) -> Result<Self, ChainError> {
+        let mut blocks = blocks.into_iter();
+        let genesis = blocks.next().ok_or(ChainError::EmptyChain)?;
+        Self::validate_genesis(&genesis)?;
+        let mut blockchain = Blockchain {
+            chain: vec![genesis],
+            pending_transactions: Vec::new(),
+            side_blocks: HashMap::new(),
+            store: None,
+        };
+        for block in blocks {
+            blockchain.accept_block(block)?;
+        }
+        Ok(blockchain)
+    }
+
+    /// Writes every block accepted from now on to `store`. An empty store is
+    /// first seeded with the blocks already held in memory, side blocks by
+    /// height so each one follows its parent, as `from_blocks` needs.
+    pub fn attach_store(&mut self, mut store: BlockStore) -> Result<(), StoreError> {
+        if store.is_empty() {
+            let mut side_blocks: Vec<&Block> = self.side_blocks.values().collect();
+            side_blocks.sort_by(|a, b| a.header.index.cmp(&b.header.index).then_with(|| a.hash.cmp(&b.hash)));
+            for block in self.chain.iter().chain(side_blocks) {
+                store.append(block)?;
+            }
+        }
+        self.store = Some(store);
+        Ok(())
+    }
+
+    /// Writes `block` to the store, if there is one. Called before the block
+    /// changes any state, so a failed write leaves the chain as it was.
+    fn persist(&mut self, block: &Block) -> Result<(), ChainError> {
+        match &mut self.store {
+            Some(store) => store.append(block)
+                .map_err(|err| ChainError::StoreFailed { index: block.header.index, reason: err.to_string() }),
+            None => Ok(()),
+        }
+    }
+
     fn create_genesis_block(&mut self) {
         let genesis_solution = ChallengeSolution {
             challenge_id: "genesis".to_string(),
@@ -54,15 +400,14 @@ impl Blockchain {
             author: "system".to_string(),
         };
 
-        let mut genesis_block = Block {
-            index: 0,
-            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
-            transactions: vec![],
-            proof: genesis_solution,
-            previous_hash: "0".to_string(),
-            hash: String::new(),
-        };
-        genesis_block.hash = genesis_block.calculate_hash();
+        let genesis_block = Block::new(
+            0,
+            SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            "0".to_string(),
+            0,
+            Difficulty::Easy,
+            BlockBody { transactions: vec![], proof: genesis_solution, attestations: vec![] },
+        );
         self.chain.push(genesis_block);
     }
 
@@ -70,27 +415,592 @@ impl Blockchain {
         self.chain.last().unwrap()
     }
 
-    pub fn mine_new_block(&mut self, proof: ChallengeSolution, miner_address: &str) -> &Block {
+    /// Mines `proof`, an answer to version `challenge_version` of its
+    /// challenge, on top of the current tip, paying the reward for `difficulty`.
+    pub fn mine_new_block(&mut self, proof: ChallengeSolution, challenge_version: u32, difficulty: Difficulty, attestations: Vec<Attestation>, miner_address: &str) -> Result<&Block, ChainError> {
         let reward_tx = Transaction {
-            sender: "0".to_string(), // System reward
+            sender: SYSTEM_SENDER.to_string(),
             recipient: miner_address.to_string(),
-            amount: 100.0, // 100 DevCoin (DVC)
+            amount: block_reward(difficulty),
+            public_key: String::new(),
+            nonce: 0,
+            signature: String::new(),
         };
-        self.pending_transactions.push(reward_tx);
+        let mut transactions = self.pending_transactions.clone();
+        transactions.push(reward_tx);
 
         let previous_hash = self.last_block().hash.clone();
-        let mut new_block = Block {
-            index: self.chain.len() as u64,
-            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
-            transactions: self.pending_transactions.clone(),
-            proof,
+        let new_block = Block::new(
+            self.chain.len() as u64,
+            SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
             previous_hash,
-            hash: String::new(),
-        };
-        new_block.hash = new_block.calculate_hash();
-        
-        self.pending_transactions = Vec::new();
+            challenge_version,
+            difficulty,
+            BlockBody { transactions, proof, attestations },
+        );
+        Self::validate_challenge(&new_block)?;
+        self.persist(&new_block)?;
+        self.pending_transactions.clear();
         self.chain.push(new_block);
-        self.chain.last().unwrap()
+        Ok(self.chain.last().unwrap())
+    }
+
+    /// Balances and nonces as of the current tip.
+    pub fn confirmed_accounts(&self) -> AccountState {
+        AccountState::from_blocks(&self.chain)
+    }
+
+    /// Balances and nonces once every queued transaction has been applied.
+    pub fn pending_accounts(&self) -> AccountState {
+        let mut accounts = self.confirmed_accounts();
+        for tx in &self.pending_transactions {
+            let _ = accounts.apply(tx);
+        }
+        accounts
+    }
+
+    /// Queues a user transaction for the next block after checking its signature,
+    /// its nonce and that the sender can afford it on top of what is already queued.
+    pub fn add_transaction(&mut self, tx: Transaction) -> Result<(), TransactionError> {
+        if tx.sender == SYSTEM_SENDER {
+            return Err(TransactionError::ReservedSender);
+        }
+        tx.verify_signature()?;
+        self.pending_accounts().apply(&tx)?;
+        self.pending_transactions.push(tx);
+        Ok(())
+    }
+
+    /// Walks the active chain from genesis, checking every block against its
+    /// parent (hash, link, index, version, timestamp, commitments, challenge,
+    /// reward and transactions) and returns the first rule violation found.
+    pub fn validate(&self) -> Result<(), ChainError> {
+        let genesis = self.chain.first().ok_or(ChainError::EmptyChain)?;
+        Self::validate_genesis(genesis)?;
+        let mut accounts = AccountState::default();
+        for pair in self.chain.windows(2) {
+            Self::validate_block(&pair[0], &pair[1], &mut accounts)?;
+        }
+        Ok(())
     }
-}
\ No newline at end of file
+
+    /// Takes in a block produced elsewhere. A block on the active tip is fully
+    /// validated and appended; anything else is kept on a side branch, and if
+    /// that branch becomes heavier than the active chain the node reorganizes.
+    /// The block is stored before any state changes, and is not taken if
+    /// that fails.
+    pub fn accept_block(&mut self, block: Block) -> Result<AcceptOutcome, ChainError> {
+        let index = block.header.index;
+        if self.side_blocks.contains_key(&block.hash) || self.chain.iter().any(|b| b.hash == block.hash) {
+            return Err(ChainError::DuplicateBlock { index });
+        }
+
+        if block.header.previous_hash == self.last_block().hash {
+            let mut accounts = self.confirmed_accounts();
+            Self::validate_block(self.last_block(), &block, &mut accounts)?;
+            self.persist(&block)?;
+            self.chain.push(block);
+            self.prune_pending();
+            return Ok(AcceptOutcome::Extended);
+        }
+
+        // Bodies on a side branch are only checked against the account state
+        // of that branch, which happens if and when it is switched to.
+        let parent = self.find_header(&block.header.previous_hash).ok_or(ChainError::UnknownParent { index })?;
+        Self::validate_header(parent, &block.header, &block.hash)?;
+        Self::validate_commitments(&block)?;
+        let tip = block.hash.clone();
+        let stored = block.clone();
+        self.side_blocks.insert(tip.clone(), block);
+
+        let Some((fork_index, branch)) = self.branch_from_main(&tip) else {
+            self.side_blocks.remove(&tip);
+            return Err(ChainError::UnknownParent { index });
+        };
+        let branch_weight: u64 = branch.iter().map(|hash| block_weight(&self.side_blocks[hash])).sum();
+        let active_weight: u64 = self.chain[fork_index + 1..].iter().map(block_weight).sum();
+        let heavier = branch_weight > active_weight;
+        // A branch that would win is checked before the block is stored, so
+        // the store never holds a block that breaks the chain on reload.
+        if heavier {
+            self.check_branch(fork_index, &branch)?;
+        }
+        if let Err(err) = self.persist(&stored) {
+            self.side_blocks.remove(&tip);
+            return Err(err);
+        }
+        if !heavier {
+            return Ok(AcceptOutcome::SideBranch);
+        }
+        Ok(AcceptOutcome::Reorganized(self.switch_to(fork_index, &branch)))
+    }
+
+    /// The header of a known block, on the active chain or a side branch.
+    pub fn find_header(&self, hash: &str) -> Option<&BlockHeader> {
+        self.side_blocks.get(hash)
+            .or_else(|| self.chain.iter().rev().find(|block| block.hash == hash))
+            .map(|block| &block.header)
+    }
+
+    /// Walks back from a side block to the active chain. Returns the index of
+    /// the fork point on the active chain and the branch hashes, oldest first,
+    /// or `None` if the branch does not lead back to the active chain.
+    fn branch_from_main(&self, tip: &str) -> Option<(usize, Vec<String>)> {
+        let mut branch = vec![tip.to_string()];
+        let mut parent_hash = &self.side_blocks[tip].header.previous_hash;
+        while let Some(parent) = self.side_blocks.get(parent_hash) {
+            branch.push(parent.hash.clone());
+            parent_hash = &parent.header.previous_hash;
+        }
+        branch.reverse();
+        let fork_index = self.chain.iter().position(|block| &block.hash == parent_hash)?;
+        Some((fork_index, branch))
+    }
+
+    /// Removes a side block and every side block built on it.
+    fn drop_subtree(&mut self, root: &str) {
+        let mut dropped = vec![root.to_string()];
+        while let Some(hash) = dropped.pop() {
+            self.side_blocks.remove(&hash);
+            dropped.extend(self.side_blocks.values()
+                .filter(|block| block.header.previous_hash == hash)
+                .map(|block| block.hash.clone()));
+        }
+    }
+
+    /// Replays `branch` on top of the active chain up to `fork_index`. A
+    /// block that is not valid there is dropped with everything built on it,
+    /// on this branch or any other, since none of it can become active.
+    fn check_branch(&mut self, fork_index: usize, branch: &[String]) -> Result<(), ChainError> {
+        let mut accounts = AccountState::from_blocks(&self.chain[..=fork_index]);
+        for (i, hash) in branch.iter().enumerate() {
+            let parent = if i == 0 { &self.chain[fork_index] } else { &self.side_blocks[&branch[i - 1]] };
+            if let Err(err) = Self::validate_block(parent, &self.side_blocks[hash], &mut accounts) {
+                self.drop_subtree(hash);
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes a checked `branch` the active chain. Transactions from the
+    /// abandoned blocks go back to the queue when they are still valid.
+    fn switch_to(&mut self, fork_index: usize, branch: &[String]) -> Reorg {
+        let connected: Vec<Block> = branch.iter().map(|hash| self.side_blocks[hash].clone()).collect();
+        let disconnected = self.chain.split_off(fork_index + 1);
+        for hash in branch {
+            self.side_blocks.remove(hash);
+        }
+        for block in &disconnected {
+            self.side_blocks.insert(block.hash.clone(), block.clone());
+        }
+        self.chain.extend(connected.iter().cloned());
+
+        let returned = disconnected.iter()
+            .flat_map(|block| block.body.transactions.iter())
+            .filter(|tx| tx.sender != SYSTEM_SENDER)
+            .cloned();
+        self.pending_transactions.splice(0..0, returned);
+        self.prune_pending();
+
+        Reorg { disconnected, connected }
+    }
+
+    /// Drops queued transactions that the active chain has included or invalidated.
+    fn prune_pending(&mut self) {
+        let mut accounts = self.confirmed_accounts();
+        self.pending_transactions.retain(|tx| accounts.apply(tx).is_ok());
+    }
+
+    fn validate_genesis(genesis: &Block) -> Result<(), ChainError> {
+        let header = &genesis.header;
+        if !(MIN_BLOCK_VERSION..=BLOCK_VERSION).contains(&header.version) {
+            return Err(ChainError::UnsupportedVersion { index: 0, version: header.version });
+        }
+        if header.index != 0 {
+            return Err(ChainError::InvalidGenesis { reason: "index is not 0" });
+        }
+        if header.previous_hash != "0" {
+            return Err(ChainError::InvalidGenesis { reason: "previous_hash is not \"0\"" });
+        }
+        if genesis.hash != header.calculate_hash() {
+            return Err(ChainError::HashMismatch { index: 0 });
+        }
+        if !genesis.body.transactions.is_empty() {
+            return Err(ChainError::InvalidGenesis { reason: "genesis must not carry transactions" });
+        }
+        Self::validate_commitments(genesis)
+    }
+
+    /// The header-only rules: version, index, hash, link to the parent and
+    /// timestamp. Neither block's body is needed to check these.
+    pub fn validate_header(parent: &BlockHeader, header: &BlockHeader, hash: &str) -> Result<(), ChainError> {
+        let expected = parent.index + 1;
+        if !(MIN_BLOCK_VERSION..=BLOCK_VERSION).contains(&header.version) {
+            return Err(ChainError::UnsupportedVersion { index: header.index, version: header.version });
+        }
+        if header.version < parent.version {
+            return Err(ChainError::VersionDowngrade { index: header.index });
+        }
+        if header.index != expected {
+            return Err(ChainError::IndexMismatch { index: header.index, expected });
+        }
+        if hash != header.calculate_hash() {
+            return Err(ChainError::HashMismatch { index: header.index });
+        }
+        if header.previous_hash != parent.calculate_hash() {
+            return Err(ChainError::BrokenLink { index: header.index });
+        }
+        if header.timestamp < parent.timestamp {
+            return Err(ChainError::TimestampBeforeParent { index: header.index });
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        if header.timestamp > now + MAX_FUTURE_DRIFT_SECS {
+            return Err(ChainError::TimestampInFuture { index: header.index });
+        }
+        Ok(())
+    }
+
+    /// Checks `block` against its parent and applies its transactions to `accounts`.
+    fn validate_block(parent: &Block, block: &Block, accounts: &mut AccountState) -> Result<(), ChainError> {
+        Self::validate_header(&parent.header, &block.header, &block.hash)?;
+        Self::validate_commitments(block)?;
+        Self::validate_challenge(block)?;
+        Self::validate_reward(block)?;
+        let index = block.header.index;
+        for (position, tx) in block.body.transactions.iter().enumerate() {
+            if tx.sender != SYSTEM_SENDER {
+                tx.verify_signature()
+                    .map_err(|reason| ChainError::InvalidTransaction { index, position, reason })?;
+            }
+            accounts.apply(tx)
+                .map_err(|reason| ChainError::InvalidTransaction { index, position, reason })?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the body is the one the header commits to, and that every
+    /// attestation is a signed pass for this block's solution and parent.
+    /// How many attestations a block needs, and from whom, is node policy
+    /// (see `verifiers`), not a chain rule.
+    fn validate_commitments(block: &Block) -> Result<(), ChainError> {
+        let header = &block.header;
+        if header.tx_root != block.body.tx_root() {
+            return Err(ChainError::TxRootMismatch { index: header.index });
+        }
+        if header.solution_hash != block.body.solution_hash() {
+            return Err(ChainError::SolutionHashMismatch { index: header.index });
+        }
+        if header.version >= 3 && header.attestation_root != block.body.attestation_root() {
+            return Err(ChainError::AttestationRootMismatch { index: header.index });
+        }
+        for (position, attestation) in block.body.attestations.iter().enumerate() {
+            attestation.check(&header.solution_hash, &block.body.proof.challenge_id, &header.previous_hash)
+                .map_err(|reason| ChainError::InvalidAttestation { index: header.index, position, reason })?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the block answers the challenge the registry selects for
+    /// its parent, in the registry's version and tier. Blocks before version
+    /// 4 do not record a challenge version and are not checked.
+    fn validate_challenge(block: &Block) -> Result<(), ChainError> {
+        if block.header.version < 4 {
+            return Ok(());
+        }
+        ChallengeRegistry::global().check_block(block)
+            .map_err(|reason| ChainError::WrongChallenge { index: block.header.index, reason })
+    }
+
+    fn validate_reward(block: &Block) -> Result<(), ChainError> {
+        let index = block.header.index;
+        let mut rewards = block.body.transactions.iter().filter(|tx| tx.sender == SYSTEM_SENDER);
+        let reward = rewards.next().ok_or(ChainError::InvalidReward { index, reason: "missing reward transaction" })?;
+        if rewards.next().is_some() {
+            return Err(ChainError::InvalidReward { index, reason: "more than one reward transaction" });
+        }
+        if reward.amount != block_reward(block.header.difficulty) {
+            return Err(ChainError::InvalidReward { index, reason: "reward amount is not the block reward for its difficulty" });
+        }
+        if reward.recipient.is_empty() {
+            return Err(ChainError::InvalidReward { index, reason: "reward has no recipient" });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn golden_body() -> BlockBody {
+        BlockBody {
+            transactions: vec![Transaction {
+                sender: "alice".to_string(),
+                recipient: "bob".to_string(),
+                amount: Amount::from_units(250),
+                public_key: String::new(),
+                nonce: 7,
+                signature: String::new(),
+            }],
+            proof: ChallengeSolution {
+                challenge_id: "fibonacci".to_string(),
+                language: "Python".to_string(),
+                code: "def solve(n): return n".to_string(),
+                author: "ada".to_string(),
+            },
+            attestations: vec![],
+        }
+    }
+
+    fn golden_block() -> Block {
+        Block::new(1, 1_700_000_000, "00".repeat(32), 1, Difficulty::Medium, golden_body())
+    }
+
+    #[test]
+    fn header_commits_to_the_body() {
+        let header = golden_block().header;
+        assert_eq!(header.tx_root, "f7b71a353f2033f99486e230fbd4563bec7b7dfa5888867c078e56c110d4db90");
+        assert_eq!(header.solution_hash, "3d999d3cb7b7e8e9143634595474b5f218ea66257126a1c3f4f5ded9538f7f8a");
+        assert_eq!(header.attestation_root, "df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119");
+    }
+
+    #[test]
+    fn header_encoding_matches_golden_vector() {
+        let block = golden_block();
+        let expected = [
+            "00000005",                               // version
+            "0000000000000001",                       // index
+            "000000006553f100",                       // timestamp
+            &format!("00000040{}", "30".repeat(64)),  // previous_hash
+            &format!("00000040{}", hex::encode(b"f7b71a353f2033f99486e230fbd4563bec7b7dfa5888867c078e56c110d4db90")),  // tx_root
+            &format!("00000040{}", hex::encode(b"3d999d3cb7b7e8e9143634595474b5f218ea66257126a1c3f4f5ded9538f7f8a")),  // solution_hash
+            &format!("00000040{}", hex::encode(b"df3f619804a92fdb4057192dc43dd748ea778adc52bc498ce80524c014b81119")),  // attestation_root
+            "00000001",                               // challenge_version
+            "000000066d656469756d",                   // difficulty "medium"
+        ].concat();
+        assert_eq!(hex::encode(&block.header.to_canonical_bytes()), expected);
+        assert_eq!(block.hash, "fd8346684d80aa703bca1d8b60c13a8542ba5b89b4f8ea6dbc3de904f6ad65ef");
+    }
+
+    #[test]
+    fn block_encoding_round_trips() {
+        let block = golden_block();
+        let bytes = block.to_canonical_bytes();
+        assert_eq!(bytes.len(), 410);
+        assert_eq!(format!("{:x}", Sha256::digest(&bytes)), "2bd329578ac7fae197d2d60c6844ef190f1750a633843e732a1554ce78867f0b");
+
+        let decoded = Block::from_canonical_bytes(&bytes).unwrap();
+        assert_eq!(decoded.hash, block.hash);
+        assert_eq!(decoded.header.difficulty, Difficulty::Medium);
+        assert_eq!(decoded.to_canonical_bytes(), bytes);
+        assert_eq!(Block::from_canonical_bytes(&bytes[..bytes.len() - 1]).unwrap_err(), DecodeError::UnexpectedEnd);
+    }
+
+    #[test]
+    fn older_headers_leave_out_newer_fields() {
+        let block = Block::legacy(1, 1_700_000_000, "00".repeat(32), golden_body());
+        assert_eq!(block.hash, "51f77a025e32fe50e722e2dd1bba92a84debfa39a1606fb2676a7cde760d85df");
+        let decoded = Block::from_canonical_bytes(&block.to_canonical_bytes()).unwrap();
+        assert_eq!(decoded.header.version, MIN_BLOCK_VERSION);
+        assert_eq!(decoded.header.difficulty, Difficulty::Easy);
+
+        let mut bytes = block.to_canonical_bytes();
+        bytes[3] = (BLOCK_VERSION + 1) as u8;
+        assert_eq!(Block::from_canonical_bytes(&bytes).unwrap_err(), DecodeError::UnsupportedVersion(BLOCK_VERSION + 1));
+    }
+
+    /// A block on `parent` answering the challenge selected for it, paying
+    /// `reward` to `miner`. Different miners give different hashes.
+    fn child_paying(parent: &Block, miner: &str, reward: Amount) -> Block {
+        let challenge = ChallengeRegistry::install_built_in().select(parent.header.index + 1, &parent.hash);
+        let reward_tx = Transaction {
+            sender: SYSTEM_SENDER.to_string(),
+            recipient: miner.to_string(),
+            amount: reward,
+            public_key: String::new(),
+            nonce: 0,
+            signature: String::new(),
+        };
+        let proof = ChallengeSolution {
+            challenge_id: challenge.id.clone(),
+            language: "Python".to_string(),
+            code: format!("# mined by {}", miner),
+            author: miner.to_string(),
+        };
+        Block::new(
+            parent.header.index + 1,
+            parent.header.timestamp,
+            parent.hash.clone(),
+            challenge.version,
+            challenge.difficulty,
+            BlockBody { transactions: vec![reward_tx], proof, attestations: vec![] },
+        )
+    }
+
+    fn child(parent: &Block, miner: &str) -> Block {
+        let difficulty = ChallengeRegistry::install_built_in().select(parent.header.index + 1, &parent.hash).difficulty;
+        child_paying(parent, miner, block_reward(difficulty))
+    }
+
+    fn tip_hash(blockchain: &Blockchain) -> &str {
+        &blockchain.last_block().hash
+    }
+
+    #[test]
+    fn heavier_branch_wins_and_a_tie_keeps_the_active_chain() {
+        let mut blockchain = Blockchain::new();
+        let genesis = blockchain.chain[0].clone();
+        let a1 = child(&genesis, "alice");
+        assert!(matches!(blockchain.accept_block(a1.clone()), Ok(AcceptOutcome::Extended)));
+
+        let b1 = child(&genesis, "bob");
+        assert!(matches!(blockchain.accept_block(b1.clone()), Ok(AcceptOutcome::SideBranch)));
+        assert_eq!(tip_hash(&blockchain), a1.hash);
+
+        let b2 = child(&b1, "bob");
+        let reorg = match blockchain.accept_block(b2.clone()) {
+            Ok(AcceptOutcome::Reorganized(reorg)) => reorg,
+            other => panic!("expected a reorganization, got {:?}", other),
+        };
+        let hashes = |blocks: &[Block]| blocks.iter().map(|block| block.hash.clone()).collect::<Vec<_>>();
+        assert_eq!(hashes(&reorg.disconnected), vec![a1.hash.clone()]);
+        assert_eq!(hashes(&reorg.connected), vec![b1.hash.clone(), b2.hash.clone()]);
+        assert_eq!(hashes(&blockchain.chain), vec![genesis.hash, b1.hash, b2.hash]);
+        assert!(blockchain.side_blocks.contains_key(&a1.hash));
+        assert_eq!(blockchain.confirmed_accounts().balance("alice"), Amount::ZERO);
+
+        assert!(matches!(blockchain.accept_block(a1), Err(ChainError::DuplicateBlock { index: 1 })));
+    }
+
+    #[test]
+    fn invalid_block_on_a_winning_branch_is_dropped_with_its_descendants() {
+        let mut blockchain = Blockchain::new();
+        let genesis = blockchain.chain[0].clone();
+        let a1 = child(&genesis, "alice");
+        let a2 = child(&a1, "alice");
+        blockchain.accept_block(a1).unwrap();
+        blockchain.accept_block(a2.clone()).unwrap();
+
+        // The wrong reward is a body rule, so the block is only caught when
+        // its branch would win.
+        let b1 = child(&genesis, "bob");
+        let b2 = child_paying(&b1, "bob", Amount::from_units(1));
+        let b3 = child(&b2, "bob");
+        assert!(matches!(blockchain.accept_block(b1.clone()), Ok(AcceptOutcome::SideBranch)));
+        assert!(matches!(blockchain.accept_block(b2.clone()), Ok(AcceptOutcome::SideBranch)));
+        assert!(matches!(blockchain.accept_block(b3.clone()), Err(ChainError::InvalidReward { index: 2, .. })));
+
+        assert_eq!(tip_hash(&blockchain), a2.hash);
+        assert!(blockchain.side_blocks.contains_key(&b1.hash));
+        assert!(!blockchain.side_blocks.contains_key(&b2.hash));
+        assert!(!blockchain.side_blocks.contains_key(&b3.hash));
+        assert!(matches!(blockchain.accept_block(child(&b3, "bob")), Err(ChainError::UnknownParent { index: 4 })));
+    }
+
+    #[test]
+    fn block_with_an_unknown_parent_is_refused() {
+        let mut blockchain = Blockchain::new();
+        let genesis = blockchain.chain[0].clone();
+        let orphan = child(&child(&genesis, "carol"), "carol");
+        assert!(matches!(blockchain.accept_block(orphan), Err(ChainError::UnknownParent { index: 2 })));
+        assert!(blockchain.side_blocks.is_empty());
+        assert_eq!(blockchain.chain.len(), 1);
+    }
+
+    /// Genesis and three blocks on it, put on the chain without `accept_block`.
+    fn unchecked_chain() -> Blockchain {
+        let mut blockchain = Blockchain::new();
+        for miner in ["alice", "bob", "carol"] {
+            let block = child(blockchain.last_block(), miner);
+            blockchain.chain.push(block);
+        }
+        blockchain
+    }
+
+    #[test]
+    fn validate_accepts_a_well_formed_chain() {
+        assert_eq!(unchecked_chain().validate(), Ok(()));
+        assert_eq!(Blockchain::new().validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_names_a_tampered_hash() {
+        let mut blockchain = unchecked_chain();
+        blockchain.chain[2].header.timestamp += 1;
+        assert_eq!(blockchain.validate(), Err(ChainError::HashMismatch { index: 2 }));
+    }
+
+    #[test]
+    fn validate_names_a_broken_link() {
+        let mut blockchain = unchecked_chain();
+        let block = &mut blockchain.chain[3];
+        block.header.previous_hash = "ab".repeat(32);
+        block.hash = block.header.calculate_hash();
+        assert_eq!(blockchain.validate(), Err(ChainError::BrokenLink { index: 3 }));
+    }
+
+    #[test]
+    fn validate_names_a_wrong_reward() {
+        let mut blockchain = unchecked_chain();
+        let parent = blockchain.chain[0].clone();
+        let difficulty = ChallengeRegistry::install_built_in().select(1, &parent.hash).difficulty;
+        blockchain.chain[1] = child_paying(&parent, "alice", block_reward(difficulty).checked_add(Amount::from_units(1)).unwrap());
+        blockchain.chain.truncate(2);
+        assert!(matches!(blockchain.validate(), Err(ChainError::InvalidReward { index: 1, .. })));
+    }
+
+    #[test]
+    fn each_tier_pays_its_multiple_of_the_easy_reward() {
+        let genesis = Blockchain::new().chain[0].clone();
+        let tiers = [(Difficulty::Easy, 100), (Difficulty::Medium, 200), (Difficulty::Hard, 300), (Difficulty::Expert, 500)];
+        for (difficulty, dvc) in tiers {
+            assert_eq!(block_reward(difficulty), Amount::from_dvc(dvc));
+            let mut block = child_paying(&genesis, "alice", Amount::from_dvc(dvc));
+            block.header.difficulty = difficulty;
+            assert_eq!(Blockchain::validate_reward(&block), Ok(()), "{}", difficulty.as_str());
+        }
+    }
+
+    #[test]
+    fn a_block_claiming_more_than_its_tier_pays_is_refused() {
+        let genesis = Blockchain::new().chain[0].clone();
+        let mut block = child_paying(&genesis, "alice", block_reward(Difficulty::Expert));
+        block.header.difficulty = Difficulty::Hard;
+        assert!(matches!(Blockchain::validate_reward(&block), Err(ChainError::InvalidReward { index: 1, .. })));
+
+        let mut blockchain = Blockchain::new();
+        let difficulty = ChallengeRegistry::install_built_in().select(1, &genesis.hash).difficulty;
+        let greedy = child_paying(&genesis, "alice", block_reward(difficulty).checked_add(BLOCK_REWARD).unwrap());
+        assert!(matches!(blockchain.accept_block(greedy), Err(ChainError::InvalidReward { index: 1, .. })));
+        assert_eq!(blockchain.chain.len(), 1);
+        assert_eq!(blockchain.confirmed_accounts().balance("alice"), Amount::ZERO);
+    }
+
+    #[test]
+    fn attached_store_reopens_with_side_blocks_after_their_parents() {
+        // Side blocks are held in a HashMap; try a few to vary its order.
+        for _ in 0..8 {
+            let mut blockchain = Blockchain::new();
+            let genesis = blockchain.chain[0].clone();
+            let a1 = child(&genesis, "alice");
+            let b1 = child(&genesis, "bob");
+            let b2 = child(&b1, "bob");
+            let b3 = child(&b2, "bob");
+            blockchain.accept_block(a1.clone()).unwrap();
+            blockchain.accept_block(child(&a1, "alice")).unwrap();
+            blockchain.accept_block(child(&blockchain.chain[2], "alice")).unwrap();
+            for block in [b1, b2, b3] {
+                assert!(matches!(blockchain.accept_block(block), Ok(AcceptOutcome::SideBranch)));
+            }
+
+            let dir = tempfile::tempdir().unwrap();
+            let (store, _) = BlockStore::open(dir.path()).unwrap();
+            blockchain.attach_store(store).unwrap();
+            let (_, stored) = BlockStore::open(dir.path()).unwrap();
+            let reopened = Blockchain::from_blocks(stored).unwrap();
+            assert_eq!(reopened.last_block().hash, blockchain.last_block().hash);
+            assert_eq!(reopened.side_blocks.len(), 3);
+        }
+    }
+}
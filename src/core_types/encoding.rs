@@ -0,0 +1,189 @@
+// Canonical binary encoding used for hashing, signing and the wire format.
+//
+// Every value has exactly one encoding, independent of serde or field order:
+//   u32 / u64        fixed width, big-endian
+//   bytes / string   u32 big-endian length, then the raw (UTF-8) bytes
+//   sequence         u32 big-endian element count, then each element
+// Structures are their fields in declaration order. Top-level values that
+// may change shape (blocks) start with their own version number.
+use std::fmt;
+
+#[derive(Default)]
+pub struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Encoder::default()
+    }
+
+    pub fn u32(&mut self, value: u32) -> &mut Self {
+        self.buf.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn u64(&mut self, value: u64) -> &mut Self {
+        self.buf.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn bytes(&mut self, value: &[u8]) -> &mut Self {
+        self.len(value.len());
+        self.buf.extend_from_slice(value);
+        self
+    }
+
+    pub fn str(&mut self, value: &str) -> &mut Self {
+        self.bytes(value.as_bytes())
+    }
+
+    pub fn seq<T: Canonical>(&mut self, items: &[T]) -> &mut Self {
+        self.len(items.len());
+        for item in items {
+            item.encode(self);
+        }
+        self
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+
+    fn len(&mut self, len: usize) {
+        let len = u32::try_from(len).expect("Encoded field is larger than 4 GiB");
+        self.u32(len);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    UnexpectedEnd,
+    InvalidUtf8,
+    UnsupportedVersion(u32),
+    TrailingBytes,
+    InvalidField(&'static str),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd => write!(f, "input ended in the middle of a value"),
+            DecodeError::InvalidUtf8 => write!(f, "string field is not valid UTF-8"),
+            DecodeError::UnsupportedVersion(version) => write!(f, "unsupported encoding version {}", version),
+            DecodeError::TrailingBytes => write!(f, "unexpected bytes after the end of the value"),
+            DecodeError::InvalidField(field) => write!(f, "field {} holds an invalid value", field),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+pub struct Decoder<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Decoder { data }
+    }
+
+    pub fn u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, DecodeError> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub fn bytes(&mut self) -> Result<&'a [u8], DecodeError> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    pub fn string(&mut self) -> Result<String, DecodeError> {
+        let bytes = self.bytes()?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::InvalidUtf8)
+    }
+
+    pub fn seq<T: Canonical>(&mut self) -> Result<Vec<T>, DecodeError> {
+        let count = self.u32()? as usize;
+        // Every element takes at least one byte, which bounds the allocation.
+        let mut items = Vec::with_capacity(count.min(self.data.len()));
+        for _ in 0..count {
+            items.push(T::decode(self)?);
+        }
+        Ok(items)
+    }
+
+    /// Fails unless every input byte has been consumed.
+    pub fn finish(self) -> Result<(), DecodeError> {
+        if self.data.is_empty() { Ok(()) } else { Err(DecodeError::TrailingBytes) }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if self.data.len() < len {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+        let (head, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(head)
+    }
+}
+
+/// A type with a single, stable binary representation.
+pub trait Canonical: Sized {
+    fn encode(&self, enc: &mut Encoder);
+    fn decode(dec: &mut Decoder) -> Result<Self, DecodeError>;
+
+    fn to_canonical_bytes(&self) -> Vec<u8> {
+        let mut enc = Encoder::new();
+        self.encode(&mut enc);
+        enc.finish()
+    }
+
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut dec = Decoder::new(bytes);
+        let value = Self::decode(&mut dec)?;
+        dec.finish()?;
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_fields_big_endian_with_length_prefixes() {
+        let mut enc = Encoder::new();
+        enc.u32(1).u64(2).str("ab").bytes(&[0xff]);
+        assert_eq!(enc.finish(), [
+            0, 0, 0, 1,
+            0, 0, 0, 0, 0, 0, 0, 2,
+            0, 0, 0, 2, b'a', b'b',
+            0, 0, 0, 1, 0xff,
+        ]);
+    }
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let mut enc = Encoder::new();
+        enc.u32(7).u64(u64::MAX).str("dévchain");
+        let bytes = enc.finish();
+        let mut dec = Decoder::new(&bytes);
+        assert_eq!(dec.u32(), Ok(7));
+        assert_eq!(dec.u64(), Ok(u64::MAX));
+        assert_eq!(dec.string().as_deref(), Ok("dévchain"));
+        assert_eq!(dec.finish(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_truncated_and_trailing_input() {
+        assert_eq!(Decoder::new(&[0, 0, 0, 3, b'a']).string(), Err(DecodeError::UnexpectedEnd));
+        assert_eq!(Decoder::new(&[0, 0, 0, 1, 0xff]).string(), Err(DecodeError::InvalidUtf8));
+        let mut dec = Decoder::new(&[0, 0, 0, 1, 9]);
+        assert_eq!(dec.u32(), Ok(1));
+        assert_eq!(dec.finish(), Err(DecodeError::TrailingBytes));
+    }
+}
@@ -0,0 +1,120 @@
+// Chain layout written before amounts became fixed-point `Amount`s: amounts
+// were `f64` DVC and the block hash covered that float formatting. These
+// types exist only to read such a chain, check it against its original
+// hashes, and rebuild it in the current layout.
+use serde::{Serialize, Deserialize};
+use sha2::{Sha256, Digest};
+use crate::challenges::challenge_trait::ChallengeSolution;
+use crate::core_types::amount::{Amount, UNITS_PER_DVC};
+use crate::core_types::blockchain::{Block, BlockBody, Blockchain, ChainError};
+use crate::core_types::transaction::{Transaction, TransactionError};
+
+#[derive(Serialize, Deserialize, Clone)]
+struct LegacyTransaction {
+    sender: String,
+    recipient: String,
+    amount: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct LegacyBlock {
+    index: u64,
+    timestamp: u64,
+    transactions: Vec<LegacyTransaction>,
+    proof: ChallengeSolution,
+    previous_hash: String,
+    hash: String,
+}
+
+impl LegacyBlock {
+    fn legacy_hash(&self) -> String {
+        let mut block_data = self.clone();
+        block_data.hash = String::new();
+        let serialized = serde_json::to_string(&block_data).expect("Failed to serialize block");
+        format!("{:x}", Sha256::digest(serialized.as_bytes()))
+    }
+}
+
+// Queued transactions are not carried over: in this layout they could only
+// be unsigned, and the current chain refuses those.
+#[derive(Deserialize)]
+pub struct LegacyBlockchain {
+    chain: Vec<LegacyBlock>,
+}
+
+impl LegacyBlockchain {
+    /// Verifies every block against its original hash and link, then
+    /// converts the amounts and re-seals the chain with current hashes.
+    pub fn migrate(self) -> Result<Blockchain, ChainError> {
+        let mut chain: Vec<Block> = Vec::with_capacity(self.chain.len());
+        let mut legacy_parent_hash = "0".to_string();
+
+        for legacy in self.chain {
+            if legacy.hash != legacy.legacy_hash() {
+                return Err(ChainError::HashMismatch { index: legacy.index });
+            }
+            if legacy.previous_hash != legacy_parent_hash {
+                return Err(ChainError::BrokenLink { index: legacy.index });
+            }
+            legacy_parent_hash = legacy.hash.clone();
+
+            let mut transactions = Vec::with_capacity(legacy.transactions.len());
+            for (position, tx) in legacy.transactions.into_iter().enumerate() {
+                let amount = dvc_to_amount(tx.amount).ok_or(ChainError::InvalidTransaction {
+                    index: legacy.index,
+                    position,
+                    reason: TransactionError::InvalidAmount,
+                })?;
+                transactions.push(Transaction {
+                    sender: tx.sender,
+                    recipient: tx.recipient,
+                    amount,
+                    public_key: String::new(),
+                    nonce: 0,
+                    signature: String::new(),
+                });
+            }
+
+            let previous_hash = chain.last().map_or_else(|| "0".to_string(), |parent: &Block| parent.hash.clone());
+            let body = BlockBody { transactions, proof: legacy.proof, attestations: Vec::new() };
+            chain.push(Block::legacy(legacy.index, legacy.timestamp, previous_hash, body));
+        }
+
+        Blockchain::from_blocks(chain)
+    }
+}
+
+fn dvc_to_amount(dvc: f64) -> Option<Amount> {
+    let units = (dvc * UNITS_PER_DVC as f64).round();
+    // `u64::MAX as f64` rounds up to 2^64, which is already out of range.
+    if !units.is_finite() || units < 0.0 || units >= u64::MAX as f64 {
+        return None;
+    }
+    Some(Amount::from_units(units as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn float_amounts_round_to_the_nearest_unit() {
+        assert_eq!(dvc_to_amount(100.0), Some(Amount::from_dvc(100)));
+        assert_eq!(dvc_to_amount(0.1 + 0.2), Some(Amount::from_units(30_000_000)));
+        assert_eq!(dvc_to_amount(0.1 + 0.2).unwrap().to_string(), "0.3");
+        assert_eq!(dvc_to_amount(0.000000014), Some(Amount::from_units(1)));
+        assert_eq!(dvc_to_amount(0.0), Some(Amount::ZERO));
+    }
+
+    #[test]
+    fn amounts_outside_the_unit_range_are_refused() {
+        // The largest whole DVC amount a u64 of units can hold.
+        let largest = dvc_to_amount(184_467_440_737.0).unwrap();
+        assert!(largest.units().abs_diff(18_446_744_073_700_000_000) < 4096);
+        assert_eq!(dvc_to_amount(u64::MAX as f64 / UNITS_PER_DVC as f64), None);
+        assert_eq!(dvc_to_amount(1e12), None);
+        assert_eq!(dvc_to_amount(-0.5), None);
+        assert_eq!(dvc_to_amount(f64::NAN), None);
+        assert_eq!(dvc_to_amount(f64::INFINITY), None);
+    }
+}
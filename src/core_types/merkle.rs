@@ -0,0 +1,142 @@
+// Binary Merkle tree over SHA-256, used for the transaction root of a block.
+//
+// Leaves are hashed as H(0x00 || data) and inner nodes as H(0x01 || left || right),
+// so a leaf can never be passed off as an inner node. When a level has an odd
+// number of nodes the last one is carried up unchanged rather than duplicated.
+use serde::{Serialize, Deserialize};
+use sha2::{Sha256, Digest};
+use crate::core_types::hex;
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+pub type Hash = [u8; 32];
+
+pub fn leaf_hash(data: &[u8]) -> Hash {
+    Sha256::new().chain_update([LEAF_PREFIX]).chain_update(data).finalize().into()
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    Sha256::new().chain_update([NODE_PREFIX]).chain_update(left).chain_update(right).finalize().into()
+}
+
+/// Root of the tree over `leaves`; an empty tree has the all-zero root.
+pub fn root(leaves: &[Hash]) -> Hash {
+    if leaves.is_empty() {
+        return [0; 32];
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = level.chunks(2)
+            .map(|pair| if pair.len() == 2 { node_hash(&pair[0], &pair[1]) } else { pair[0] })
+            .collect();
+    }
+    level[0]
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// A sibling hash on the way from the leaf to the root, and which side it sits on.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProofStep {
+    pub hash: String,
+    pub side: Side,
+}
+
+/// Everything a light client needs to tie one leaf to a block's Merkle root.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MerkleProof {
+    pub leaf_index: usize,
+    pub leaf_hash: String,
+    pub path: Vec<ProofStep>,
+}
+
+impl MerkleProof {
+    pub fn build(leaves: &[Hash], leaf_index: usize) -> Option<MerkleProof> {
+        let leaf = *leaves.get(leaf_index)?;
+        let mut path = Vec::new();
+        let mut level = leaves.to_vec();
+        let mut position = leaf_index;
+        while level.len() > 1 {
+            let sibling = position ^ 1;
+            if sibling < level.len() {
+                let side = if sibling < position { Side::Left } else { Side::Right };
+                path.push(ProofStep { hash: hex::encode(&level[sibling]), side });
+            }
+            level = level.chunks(2)
+                .map(|pair| if pair.len() == 2 { node_hash(&pair[0], &pair[1]) } else { pair[0] })
+                .collect();
+            position /= 2;
+        }
+
+        let proof = MerkleProof { leaf_index, leaf_hash: hex::encode(&leaf), path };
+        debug_assert!(proof.verify(&hex::encode(&root(leaves))));
+        Some(proof)
+    }
+
+    /// Recomputes the root from the leaf and its path and compares it to `expected_root`.
+    pub fn verify(&self, expected_root: &str) -> bool {
+        let Some(mut current) = decode_hash(&self.leaf_hash) else { return false };
+        for step in &self.path {
+            let Some(sibling) = decode_hash(&step.hash) else { return false };
+            current = match step.side {
+                Side::Left => node_hash(&sibling, &current),
+                Side::Right => node_hash(&current, &sibling),
+            };
+        }
+        hex::encode(&current) == expected_root
+    }
+}
+
+fn decode_hash(text: &str) -> Option<Hash> {
+    hex::decode(text)?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(count: u8) -> Vec<Hash> {
+        (0..count).map(|i| leaf_hash(&[i])).collect()
+    }
+
+    #[test]
+    fn roots_match_golden_vectors() {
+        assert_eq!(hex::encode(&root(&[])), "0".repeat(64));
+        assert_eq!(hex::encode(&root(&leaves(1))), "96a296d224f285c67bee93c30f8a309157f0daa35dc5b87e410b78630a09cfc7");
+        assert_eq!(hex::encode(&root(&leaves(2))), "a20bf9a7cc2dc8a08f5f415a71b19f6ac427bab54d24eec868b5d3103449953a");
+        // The odd third leaf is carried up, not paired with itself.
+        assert_eq!(hex::encode(&root(&leaves(3))), "3b6cccd7e3e023ff393006f030315ee7ad9eb111b022b41fba7e5b7a3973f688");
+    }
+
+    #[test]
+    fn proofs_verify_for_every_leaf() {
+        for count in 1..=7 {
+            let leaves = leaves(count);
+            let expected_root = hex::encode(&root(&leaves));
+            for index in 0..leaves.len() {
+                let proof = MerkleProof::build(&leaves, index).unwrap();
+                assert!(proof.verify(&expected_root), "leaf {} of {}", index, count);
+            }
+            assert!(MerkleProof::build(&leaves, leaves.len()).is_none());
+        }
+    }
+
+    #[test]
+    fn proof_golden_vector_and_tampering() {
+        let leaves = leaves(3);
+        let proof = MerkleProof::build(&leaves, 2).unwrap();
+        assert_eq!(proof.path.len(), 1);
+        assert_eq!(proof.path[0].side, Side::Left);
+        assert_eq!(proof.path[0].hash, "a20bf9a7cc2dc8a08f5f415a71b19f6ac427bab54d24eec868b5d3103449953a");
+
+        let mut tampered = proof.clone();
+        tampered.leaf_hash = hex::encode(&leaf_hash(&[9]));
+        assert!(!tampered.verify(&hex::encode(&root(&leaves))));
+    }
+}
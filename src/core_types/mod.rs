@@ -1,2 +1,9 @@
+pub mod accounts;
+pub mod amount;
+pub mod attestation;
 pub mod blockchain;
-pub mod transaction;
\ No newline at end of file
+pub mod encoding;
+pub mod hex;
+pub mod legacy;
+pub mod merkle;
+pub mod transaction;
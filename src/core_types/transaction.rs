@@ -1,8 +1,171 @@
 use serde::{Serialize, Deserialize}; // <-- Added Deserialize
+use std::fmt;
+use libp2p::{identity, PeerId};
+use crate::core_types::amount::Amount;
+use crate::core_types::encoding::{Canonical, DecodeError, Decoder, Encoder};
+use crate::core_types::hex;
+
+/// Domain tag mixed into every signed payload so a transaction signature
+/// can never be replayed as a signature over some other kind of message.
+const SIGNING_DOMAIN: &[u8] = b"devchain-tx-v1";
 
 #[derive(Serialize, Deserialize, Debug, Clone)] // <-- Added Deserialize
 pub struct Transaction {
     pub sender: String,
     pub recipient: String,
-    pub amount: f64,
-}
\ No newline at end of file
+    pub amount: Amount,
+    // Reward transactions from the system sender carry none of the fields
+    // below, so they are left out of the JSON for those.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub public_key: String,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub nonce: u64,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub signature: String,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+/// Why a transaction failed sender authentication.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransactionError {
+    Unsigned,
+    InvalidPublicKey,
+    InvalidSignature,
+    SenderMismatch,
+    ReservedSender,
+    InvalidAmount,
+    UnexpectedNonce { expected: u64 },
+    InsufficientFunds { available: Amount },
+    BalanceOverflow,
+}
+
+impl fmt::Display for TransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransactionError::Unsigned => write!(f, "transaction is not signed"),
+            TransactionError::InvalidPublicKey => write!(f, "public key is not a valid Ed25519 key"),
+            TransactionError::InvalidSignature => write!(f, "signature does not verify"),
+            TransactionError::SenderMismatch => write!(f, "sender is not the address of the signing key"),
+            TransactionError::ReservedSender => write!(f, "the system sender cannot submit transactions"),
+            TransactionError::InvalidAmount => write!(f, "amount must be greater than zero"),
+            TransactionError::UnexpectedNonce { expected } => write!(f, "expected nonce {}", expected),
+            TransactionError::InsufficientFunds { available } => write!(f, "insufficient funds ({} DVC available)", available),
+            TransactionError::BalanceOverflow => write!(f, "recipient balance would overflow"),
+        }
+    }
+}
+
+impl std::error::Error for TransactionError {}
+
+impl Transaction {
+    /// The address controlled by an Ed25519 key: its libp2p peer id.
+    pub fn address_for(public_key: &identity::ed25519::PublicKey) -> String {
+        PeerId::from(identity::PublicKey::from(public_key.clone())).to_string()
+    }
+
+    /// Builds a transfer from the wallet's address and signs it.
+    pub fn new_signed(keypair: &identity::ed25519::Keypair, recipient: &str, amount: Amount, nonce: u64) -> Self {
+        let mut tx = Transaction {
+            sender: Self::address_for(&keypair.public()),
+            recipient: recipient.to_string(),
+            amount,
+            public_key: hex::encode(&keypair.public().to_bytes()),
+            nonce,
+            signature: String::new(),
+        };
+        tx.signature = hex::encode(&keypair.sign(&tx.signing_bytes()));
+        tx
+    }
+
+    /// Canonical bytes covered by the signature: the domain tag followed by
+    /// every field except the signature itself.
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        let mut enc = Encoder::new();
+        enc.bytes(SIGNING_DOMAIN)
+            .str(&self.sender)
+            .str(&self.recipient)
+            .u64(self.amount.units())
+            .str(&self.public_key)
+            .u64(self.nonce);
+        enc.finish()
+    }
+
+    /// Checks that the transaction is signed by the key that owns `sender`.
+    pub fn verify_signature(&self) -> Result<(), TransactionError> {
+        if self.public_key.is_empty() || self.signature.is_empty() {
+            return Err(TransactionError::Unsigned);
+        }
+        let key_bytes = hex::decode(&self.public_key).ok_or(TransactionError::InvalidPublicKey)?;
+        let public_key = identity::ed25519::PublicKey::try_from_bytes(&key_bytes)
+            .map_err(|_| TransactionError::InvalidPublicKey)?;
+        if self.sender != Self::address_for(&public_key) {
+            return Err(TransactionError::SenderMismatch);
+        }
+        let signature = hex::decode(&self.signature).ok_or(TransactionError::InvalidSignature)?;
+        if !public_key.verify(&self.signing_bytes(), &signature) {
+            return Err(TransactionError::InvalidSignature);
+        }
+        Ok(())
+    }
+}
+
+impl Canonical for Transaction {
+    fn encode(&self, enc: &mut Encoder) {
+        enc.str(&self.sender)
+            .str(&self.recipient)
+            .u64(self.amount.units())
+            .str(&self.public_key)
+            .u64(self.nonce)
+            .str(&self.signature);
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, DecodeError> {
+        Ok(Transaction {
+            sender: dec.string()?,
+            recipient: dec.string()?,
+            amount: Amount::from_units(dec.u64()?),
+            public_key: dec.string()?,
+            nonce: dec.u64()?,
+            signature: dec.string()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unsigned() -> Transaction {
+        Transaction {
+            sender: "alice".to_string(),
+            recipient: "bob".to_string(),
+            amount: Amount::from_units(250),
+            public_key: String::new(),
+            nonce: 7,
+            signature: String::new(),
+        }
+    }
+
+    #[test]
+    fn canonical_encoding_matches_golden_vector() {
+        let bytes = unsigned().to_canonical_bytes();
+        assert_eq!(hex::encode(&bytes), "00000005616c69636500000003626f6200000000000000fa00000000000000000000000700000000");
+        let decoded = Transaction::from_canonical_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_canonical_bytes(), bytes);
+    }
+
+    #[test]
+    fn signature_covers_every_field() {
+        let keypair = identity::ed25519::Keypair::generate();
+        let tx = Transaction::new_signed(&keypair, "bob", Amount::from_dvc(3), 1);
+        assert_eq!(tx.verify_signature(), Ok(()));
+
+        let mut tampered = tx.clone();
+        tampered.amount = Amount::from_dvc(4);
+        assert_eq!(tampered.verify_signature(), Err(TransactionError::InvalidSignature));
+        assert_eq!(unsigned().verify_signature(), Err(TransactionError::Unsigned));
+    }
+}
@@ -1,7 +1,13 @@
 use serde::{Serialize, Deserialize};
 use crate::gamification::skills::{SkillTree};
 use crate::gamification::badges::{Badge};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+
+const FIRST_LEVEL_XP: u32 = 100;
+
+fn next_level_xp(current: u32) -> u32 {
+    (current as f32 * 1.5) as u32
+}
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct DeveloperProfile {
@@ -14,6 +20,10 @@ pub struct DeveloperProfile {
     pub badges: HashSet<Badge>,
     pub solved_languages: HashSet<String>,
     pub guild_id: Option<String>,
+    /// XP awarded per mined block hash, so it can be revoked if a chain
+    /// reorganization drops the block.
+    #[serde(default)]
+    pub block_xp: HashMap<String, u32>,
 }
 
 impl DeveloperProfile {
@@ -22,12 +32,13 @@ impl DeveloperProfile {
             developer_id: developer_id.to_string(),
             xp: 0,
             level: 1,
-            xp_to_next_level: 100,
+            xp_to_next_level: FIRST_LEVEL_XP,
             skill_points: 0,
             skill_tree: SkillTree::new(),
             badges: HashSet::new(),
             solved_languages: HashSet::new(),
             guild_id: None,
+            block_xp: HashMap::new(),
         }
     }
 
@@ -43,11 +54,45 @@ impl DeveloperProfile {
         }
     }
 
+    /// Awards XP for a block and remembers which block it came from.
+    pub fn credit_block(&mut self, block_hash: &str, amount: u32, language: &str) {
+        self.block_xp.insert(block_hash.to_string(), amount);
+        self.add_xp(amount, language);
+    }
+
+    /// Takes back the XP from a block that left the active chain. Levels are
+    /// recomputed from the remaining total; skill points already spent stay spent.
+    pub fn revoke_block(&mut self, block_hash: &str) {
+        let Some(amount) = self.block_xp.remove(block_hash) else { return };
+        let spent_points = (self.level - 1).saturating_sub(self.skill_points);
+
+        self.xp = self.total_xp().saturating_sub(amount);
+        self.level = 1;
+        self.xp_to_next_level = FIRST_LEVEL_XP;
+        while self.xp >= self.xp_to_next_level {
+            self.xp -= self.xp_to_next_level;
+            self.level += 1;
+            self.xp_to_next_level = next_level_xp(self.xp_to_next_level);
+        }
+        self.skill_points = (self.level - 1).saturating_sub(spent_points);
+        println!("[-] {} lost {} XP: block {} is no longer on the chain.", self.developer_id, amount, block_hash);
+    }
+
+    fn total_xp(&self) -> u32 {
+        let mut total = self.xp;
+        let mut threshold = FIRST_LEVEL_XP;
+        for _ in 1..self.level {
+            total += threshold;
+            threshold = next_level_xp(threshold);
+        }
+        total
+    }
+
     fn level_up(&mut self) {
         self.xp -= self.xp_to_next_level;
         self.level += 1;
         self.skill_points += 1;
-        self.xp_to_next_level = (self.xp_to_next_level as f32 * 1.5) as u32;
+        self.xp_to_next_level = next_level_xp(self.xp_to_next_level);
         println!("[*] DING! {} reached Level {}!", self.developer_id, self.level);
         println!("[*] You have earned 1 Skill Point! You now have {}.", self.skill_points);
     }
@@ -15,6 +15,13 @@
         TypeScript,
     }
 
+    impl Skill {
+        pub const ALL: [Skill; 10] = [
+            Skill::Python, Skill::JavaScript, Skill::Rust, Skill::Go, Skill::Java,
+            Skill::Cpp, Skill::CSharp, Skill::Swift, Skill::Kotlin, Skill::TypeScript,
+        ];
+    }
+
     #[derive(Serialize, Deserialize, Debug)]
     pub struct SkillTree {
         pub skills: HashMap<Skill, u32>,
@@ -30,6 +37,7 @@
             SkillTree { skills }
         }
 
+        #[allow(dead_code)]
         pub fn upgrade_skill(&mut self, skill: Skill) -> Result<(), &'static str> {
             let level = self.skills.entry(skill).or_insert(0);
             *level += 1;
@@ -0,0 +1,367 @@
+// Verification job queue. Submissions get a job id straight away and wait
+// in a channel; a fixed pool of worker threads runs them through the oracle
+// in parallel. Only answers to the challenge selected for the next block
+// are taken, and copies of earlier solutions are refused or flagged. A
+// passing solution is attested, by this node and by verifier peers if the
+// quorum needs more, and the node state is only locked to commit it as a
+// new block. Records of finished jobs are kept for a while to be polled,
+// then dropped.
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::app_state::AppState;
+use crate::challenges::challenge_trait::ChallengeSolution;
+use crate::challenges::definition::ChallengeDefinition;
+use crate::challenges::registry::ChallengeRegistry;
+use crate::challenges::verifier::VerificationOracle;
+use crate::core_types::attestation::Attestation;
+use crate::oracle::report::VerificationReport;
+use crate::p2p::P2PService;
+use crate::similarity::{SimilarityCheck, SimilarityMatch};
+use crate::storage::state_file;
+use crate::verifiers::VerifierSet;
+use crate::wallet::Wallet;
+
+pub type JobId = u64;
+
+/// How long the record of a finished job is kept.
+const FINISHED_JOB_TTL: Duration = Duration::from_secs(60 * 60);
+/// Most records of finished jobs kept at once; the oldest go first.
+const MAX_FINISHED_JOBS: usize = 1_000;
+
+/// Index and hash of the block a job was mined into, if it passed, or why
+/// it was not mined.
+type Outcome = Result<Option<(u64, String)>, String>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Finished,
+}
+
+impl JobStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Finished => "finished",
+        }
+    }
+}
+
+/// What is known about a job so far.
+#[derive(Debug, Clone)]
+pub struct JobRecord {
+    pub id: JobId,
+    pub challenge_id: String,
+    pub language: String,
+    pub author: String,
+    pub status: JobStatus,
+    /// Set once the job has been verified.
+    pub report: Option<VerificationReport>,
+    /// Index and hash of the block the solution was committed in, if it passed.
+    pub block: Option<(u64, String)>,
+    /// Why the solution was not mined although it passed, or was not
+    /// verified at all.
+    pub error: Option<String>,
+    /// The earlier solution this one copies, when copies are flagged
+    /// rather than refused.
+    pub similar_to: Option<SimilarityMatch>,
+    /// When the job finished, for dropping its record later.
+    pub finished_at: Option<Instant>,
+}
+
+struct Job {
+    id: JobId,
+    solution: ChallengeSolution,
+    reward_address: String,
+}
+
+/// Everything a worker needs to verify a job and commit the result.
+struct WorkerContext {
+    oracle: Arc<VerificationOracle>,
+    app: Arc<Mutex<AppState>>,
+    p2p: Arc<P2PService>,
+    wallet: Arc<Wallet>,
+    verifiers: Arc<VerifierSet>,
+    similarity: Arc<SimilarityCheck>,
+    records: Arc<Mutex<HashMap<JobId, JobRecord>>>,
+}
+
+pub struct JobQueue {
+    sender: Mutex<Sender<Job>>,
+    oracle: Arc<VerificationOracle>,
+    app: Arc<Mutex<AppState>>,
+    similarity: Arc<SimilarityCheck>,
+    records: Arc<Mutex<HashMap<JobId, JobRecord>>>,
+    next_id: Mutex<JobId>,
+}
+
+impl JobQueue {
+    /// Starts `workers` worker threads (at least one).
+    pub fn start(
+        workers: usize,
+        oracle: Arc<VerificationOracle>,
+        app: Arc<Mutex<AppState>>,
+        p2p: Arc<P2PService>,
+        wallet: Arc<Wallet>,
+        verifiers: Arc<VerifierSet>,
+        similarity: Arc<SimilarityCheck>,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let records = Arc::new(Mutex::new(HashMap::new()));
+        let context = Arc::new(WorkerContext {
+            oracle: oracle.clone(),
+            app: app.clone(),
+            p2p,
+            wallet,
+            verifiers,
+            similarity: similarity.clone(),
+            records: records.clone(),
+        });
+        for worker in 0..workers.max(1) {
+            let receiver = receiver.clone();
+            let context = context.clone();
+            thread::Builder::new()
+                .name(format!("verifier-{}", worker))
+                .spawn(move || run_worker(&receiver, &context))
+                .expect("Could not start a verification worker thread.");
+        }
+        JobQueue { sender: Mutex::new(sender), oracle, app, similarity, records, next_id: Mutex::new(1) }
+    }
+
+    /// Queues a solution to the current challenge for verification. If it
+    /// passes, the block reward goes to `reward_address`.
+    pub fn submit(&self, solution: ChallengeSolution, reward_address: String) -> Result<JobId, String> {
+        self.oracle.check_enabled()?;
+        let similar_to = {
+            let state = self.app.lock().unwrap();
+            let tip = state.blockchain.last_block();
+            let challenge = current_challenge(tip.header.index + 1, &tip.hash, &solution)?;
+            if !challenge.languages.contains_key(&solution.language) {
+                return Err(format!("challenge {} does not accept {}", solution.challenge_id, solution.language));
+            }
+            find_copy(&self.similarity, &self.oracle, &state, &solution)?
+        };
+        if solution.author.trim().is_empty() || reward_address.trim().is_empty() {
+            return Err("author and reward address must not be empty".to_string());
+        }
+
+        let id = {
+            let mut next_id = self.next_id.lock().unwrap();
+            let id = *next_id;
+            *next_id += 1;
+            id
+        };
+        self.records.lock().unwrap().insert(id, JobRecord {
+            id,
+            challenge_id: solution.challenge_id.clone(),
+            language: solution.language.clone(),
+            author: solution.author.clone(),
+            status: JobStatus::Queued,
+            report: None,
+            block: None,
+            error: None,
+            similar_to,
+            finished_at: None,
+        });
+        self.sender.lock().unwrap()
+            .send(Job { id, solution, reward_address })
+            .map_err(|_| "the verification workers have stopped".to_string())?;
+        Ok(id)
+    }
+
+    /// The record of a job, until some time after it finishes.
+    pub fn get(&self, id: JobId) -> Option<JobRecord> {
+        self.records.lock().unwrap().get(&id).cloned()
+    }
+}
+
+/// Drops the records of jobs that finished more than `FINISHED_JOB_TTL`
+/// before `now`, then the oldest finished ones past `MAX_FINISHED_JOBS`.
+fn prune_finished(records: &mut HashMap<JobId, JobRecord>, now: Instant) {
+    records.retain(|_, record| record.finished_at.is_none_or(|at| now.duration_since(at) < FINISHED_JOB_TTL));
+    let mut finished: Vec<(Instant, JobId)> = records.values()
+        .filter_map(|record| record.finished_at.map(|at| (at, record.id)))
+        .collect();
+    if finished.len() > MAX_FINISHED_JOBS {
+        finished.sort();
+        for (_, id) in &finished[..finished.len() - MAX_FINISHED_JOBS] {
+            records.remove(id);
+        }
+    }
+}
+
+fn run_worker(receiver: &Mutex<Receiver<Job>>, context: &WorkerContext) {
+    loop {
+        // Only hold the receiver while waiting, so other workers can pick up jobs.
+        let job = match receiver.lock().unwrap().recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        context.update(job.id, |record| record.status = JobStatus::Running);
+        println!("[JOBS] Job #{} started: {} in {} by {}.", job.id, job.solution.challenge_id, job.solution.language, job.solution.author);
+
+        let (report, committed) = context.verify_and_commit(&job);
+
+        match (&report, &committed) {
+            (Some(report), Ok(_)) => println!("[JOBS] Job #{} finished: {}.", job.id, report.verdict),
+            (Some(_), Err(reason)) => println!("[JOBS] Job #{} passed but was not mined: {}", job.id, reason),
+            (None, Err(reason)) => println!("[JOBS] Job #{} was not verified: {}", job.id, reason),
+            (None, Ok(_)) => unreachable!("a job is only left unverified with a reason"),
+        }
+        context.update(job.id, |record| {
+            record.status = JobStatus::Finished;
+            record.finished_at = Some(Instant::now());
+            record.report = report;
+            match committed {
+                Ok(block) => record.block = block,
+                Err(reason) => record.error = Some(reason),
+            }
+        });
+        prune_finished(&mut context.records.lock().unwrap(), Instant::now());
+    }
+}
+
+impl WorkerContext {
+    fn update(&self, id: JobId, change: impl FnOnce(&mut JobRecord)) {
+        if let Some(record) = self.records.lock().unwrap().get_mut(&id) {
+            change(record);
+        }
+    }
+
+    /// Verifies the job against cases generated from the current tip and,
+    /// if it passes and enough verifiers attest it, mines it on that tip.
+    /// A solution is checked again when another block lands first, since
+    /// its generated cases change, unless that block moved the chain on to
+    /// a different challenge.
+    fn verify_and_commit(&self, job: &Job) -> (Option<VerificationReport>, Outcome) {
+        loop {
+            let (height, parent_hash, copied) = {
+                let state = self.app.lock().unwrap();
+                let tip = state.blockchain.last_block();
+                (tip.header.index + 1, tip.hash.clone(), find_copy(&self.similarity, &self.oracle, &state, &job.solution))
+            };
+            let challenge = match current_challenge(height, &parent_hash, &job.solution) {
+                Ok(challenge) => challenge,
+                Err(reason) => return (None, Err(reason)),
+            };
+            // Checked again since the chain may have grown since submission.
+            match copied {
+                Ok(Some(found)) => {
+                    println!("[JOBS] Job #{} is flagged: the solution is {}.", job.id, found);
+                    self.update(job.id, |record| record.similar_to = Some(found));
+                }
+                Ok(None) => {}
+                Err(reason) => return (None, Err(reason)),
+            }
+            let report = self.oracle.verify(challenge, &job.solution, &parent_hash);
+            if !report.passed() {
+                return (Some(report), Ok(None));
+            }
+            let own = self.wallet.attest(&job.solution, &parent_hash, &report);
+            let attestations = match self.verifiers.collect(&job.solution, &parent_hash, &job.reward_address, own) {
+                Ok(attestations) => attestations,
+                Err(reason) => return (Some(report), Err(reason)),
+            };
+            match self.commit(job, &parent_hash, challenge, attestations) {
+                Ok(Some(block)) => return (Some(report), Ok(Some(block))),
+                Ok(None) => println!("[JOBS] Job #{}: a new block arrived during verification; checking again against it.", job.id),
+                Err(reason) => return (Some(report), Err(reason)),
+            }
+        }
+    }
+
+    /// Mines the verified solution to `challenge` into a block. Nothing is
+    /// mined if the tip is no longer `parent_hash`.
+    fn commit(&self, job: &Job, parent_hash: &str, challenge: &ChallengeDefinition, attestations: Vec<Attestation>) -> Outcome {
+        let mut state = self.app.lock().unwrap();
+        if state.blockchain.last_block().hash != parent_hash {
+            return Ok(None);
+        }
+        let block = state.blockchain.mine_new_block(job.solution.clone(), challenge.version, challenge.difficulty, attestations, &job.reward_address)
+            .map_err(|err| err.to_string())?
+            .clone();
+        println!("\n[!] Block #{} successfully mined!", block.header.index);
+        println!("[!] Hash: {}", block.hash);
+        self.p2p.broadcast_block(&block);
+        // XP is tied to the block so a reorganization can take it back.
+        state.credit_block(&block);
+        state_file::save(&state);
+        Ok(Some((block.header.index, block.hash)))
+    }
+}
+
+/// The closest earlier solution on `state`'s chain, or the reference
+/// solution, that `solution` copies.
+fn find_copy(similarity: &SimilarityCheck, oracle: &VerificationOracle, state: &AppState, solution: &ChallengeSolution) -> Result<Option<SimilarityMatch>, String> {
+    let reference = oracle.reference_solution(&solution.challenge_id, &solution.language);
+    similarity.check(solution, &state.blockchain.chain, reference)
+}
+
+/// The challenge selected for the block at `height` after `tip`, if
+/// `solution` answers it.
+fn current_challenge(height: u64, tip: &str, solution: &ChallengeSolution) -> Result<&'static ChallengeDefinition, String> {
+    let challenge = ChallengeRegistry::global().select(height, tip);
+    if challenge.id != solution.challenge_id {
+        return Err(format!("the next block answers {}, not {}", challenge.id, solution.challenge_id));
+    }
+    Ok(challenge)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: JobId, finished_at: Option<Instant>) -> JobRecord {
+        JobRecord {
+            id,
+            challenge_id: "fibonacci".to_string(),
+            language: "Python".to_string(),
+            author: "ada".to_string(),
+            status: if finished_at.is_some() { JobStatus::Finished } else { JobStatus::Running },
+            report: None,
+            block: None,
+            error: None,
+            similar_to: None,
+            finished_at,
+        }
+    }
+
+    #[test]
+    fn finished_records_expire_but_unfinished_ones_stay() {
+        let start = Instant::now();
+        let mut records: HashMap<JobId, JobRecord> = HashMap::new();
+        records.insert(1, record(1, Some(start)));
+        records.insert(2, record(2, Some(start + Duration::from_secs(60))));
+        records.insert(3, record(3, None));
+
+        prune_finished(&mut records, start + FINISHED_JOB_TTL - Duration::from_secs(1));
+        assert_eq!(records.len(), 3);
+        prune_finished(&mut records, start + FINISHED_JOB_TTL);
+        let mut kept: Vec<JobId> = records.keys().copied().collect();
+        kept.sort();
+        assert_eq!(kept, [2, 3]);
+    }
+
+    #[test]
+    fn oldest_finished_records_go_past_the_cap() {
+        let start = Instant::now();
+        let mut records: HashMap<JobId, JobRecord> = HashMap::new();
+        let extra = 5;
+        for id in 0..(MAX_FINISHED_JOBS + extra) as JobId {
+            records.insert(id, record(id, Some(start + Duration::from_millis(id))));
+        }
+        records.insert(u64::MAX, record(u64::MAX, None));
+
+        prune_finished(&mut records, start + Duration::from_secs(5));
+        assert_eq!(records.len(), MAX_FINISHED_JOBS + 1);
+        assert!((0..extra as JobId).all(|id| !records.contains_key(&id)));
+        assert!(records.contains_key(&(extra as JobId)));
+        assert!(records.contains_key(&u64::MAX));
+    }
+}
@@ -7,45 +7,146 @@ mod vm;
 mod app_state;
 mod oracle;
 mod api; // <-- Add new module
+mod wallet;
+mod storage;
+mod config;
+mod jobs;
+mod verifiers;
+mod similarity;
 
 use crate::app_state::AppState;
+use crate::challenges::definition;
+use crate::challenges::registry::ChallengeRegistry;
+use crate::challenges::oracle_store::OracleStore;
+use crate::challenges::verifier::VerificationOracle;
+use crate::config::{NodeConfig, DEFAULT_ORACLE_STORE_DIR};
+use crate::core_types::blockchain::Blockchain;
 use crate::p2p::P2PService;
 use crate::cli::run_cli;
-use std::fs::File;
-use std::io::BufReader;
+use crate::gamification::skills::Skill;
+use crate::jobs::JobQueue;
+use crate::oracle::languages::LanguageRegistry;
+use crate::oracle::runner::OracleRunner;
+use crate::oracle::sandbox;
+use crate::storage::block_store::BlockStore;
+use crate::storage::state_file::{self, BLOCKS_DIR, SCHEMA_VERSION, STATE_PATH};
+use crate::similarity::{SimilarityAction, SimilarityCheck};
+use crate::verifiers::VerifierSet;
+use crate::wallet::Wallet;
+use std::path::Path;
 use std::sync::{Arc, Mutex}; // <-- Add this
 
-const DB_PATH: &str = "devchain_state.json";
+const WALLET_PATH: &str = "devchain_wallet.key";
 const CURRENT_DEVELOPER_ID: &str = "dev_polyglot_001";
 
 fn load_app_state() -> AppState {
-    // ... (this function is unchanged)
-    match File::open(DB_PATH) {
-        Ok(file) => {
-            let reader = BufReader::new(file);
-            match serde_json::from_reader(reader) {
-                Ok(state) => {
-                    println!("[INFO] Application state loaded from {}.", DB_PATH);
-                    state
-                },
-                Err(_) => {
-                    println!("[WARN] Could not parse state file. Starting fresh.");
-                    AppState::new()
+    let (mut store, stored_blocks) = match BlockStore::open(Path::new(BLOCKS_DIR)) {
+        Ok(opened) => opened,
+        Err(err) => {
+            println!("[ERROR] Could not open the block store in {}: {}", BLOCKS_DIR, err);
+            std::process::exit(1);
+        }
+    };
+    let loaded = match state_file::load() {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            // Starting fresh here would overwrite the user's profiles on exit.
+            println!("[ERROR] Could not read {}: {}", STATE_PATH, err);
+            println!("[ERROR] Refusing to start so the file is not overwritten. Fix it or move it aside to start fresh.");
+            std::process::exit(1);
+        }
+    };
+    let (file, legacy_chain, migrated_from) = match loaded {
+        Some(loaded) => (Some(loaded.file), loaded.legacy_chain, loaded.migrated_from),
+        None => (None, None, None),
+    };
+
+    let mut state = AppState::new();
+    match (legacy_chain, stored_blocks.is_empty()) {
+        (Some(chain), true) => {
+            println!("[INFO] Moving the chain in {} to the block store in {}.", STATE_PATH, BLOCKS_DIR);
+            state.blockchain = chain;
+        }
+        (Some(chain), false) => {
+            // The state file is only left holding a chain when the node
+            // stopped before saving it after the move, so that chain is the
+            // one to keep.
+            let aside = format!("{}.old", BLOCKS_DIR);
+            println!("[WARN] {} holds a chain, but the block store in {} already has blocks.", STATE_PATH, BLOCKS_DIR);
+            println!("[INFO] Rebuilding the block store from {} (the old store is kept as {}).", STATE_PATH, aside);
+            store = set_block_store_aside(store, &aside);
+            state.blockchain = chain;
+        }
+        (None, true) => {}
+        (None, false) => {
+            let count = stored_blocks.len();
+            state.blockchain = match Blockchain::from_blocks(stored_blocks) {
+                Ok(blockchain) => blockchain,
+                Err(err) => {
+                    // Refuse to run on (and later extend) a chain we cannot trust.
+                    println!("[ERROR] Block store in {} failed validation: {}", BLOCKS_DIR, err);
+                    std::process::exit(1);
                 }
-            }
-        },
-        Err(_) => {
-            println!("[INFO] No existing state file found. Starting fresh.");
-            AppState::new()
+            };
+            println!("[INFO] Loaded {} blocks from {}.", count, BLOCKS_DIR);
+        }
+    }
+    // Replaying the blocks checked each one as it was accepted; walk the
+    // chain that came out of it once more before anything builds on it.
+    if let Err(err) = state.blockchain.validate() {
+        println!("[ERROR] The loaded chain failed validation: {}", err);
+        std::process::exit(1);
+    }
+    if let Err(err) = state.blockchain.attach_store(store) {
+        println!("[ERROR] Could not write the chain to the block store in {}: {}", BLOCKS_DIR, err);
+        std::process::exit(1);
+    }
+
+    match file {
+        Some(file) => {
+            file.restore_into(&mut state);
+            println!("[INFO] Application state loaded from {}.", STATE_PATH);
+        }
+        None => println!("[INFO] No existing state file found. Starting fresh."),
+    }
+    if let Some(version) = migrated_from {
+        // Write the new layout right away so the old one is never read twice.
+        state_file::save(&state);
+        println!("[INFO] Migrated {} from schema version {} to {} (backup kept as {}.v{}.bak).",
+            STATE_PATH, version, SCHEMA_VERSION, STATE_PATH, version);
+    }
+    state
+}
+
+/// Moves the block store to `aside` and opens a new, empty one in its place.
+fn set_block_store_aside(store: BlockStore, aside: &str) -> BlockStore {
+    drop(store);
+    let reopened = std::fs::rename(BLOCKS_DIR, aside)
+        .and_then(|()| BlockStore::open(Path::new(BLOCKS_DIR)).map_err(std::io::Error::other));
+    match reopened {
+        Ok((store, _)) => store,
+        Err(err) => {
+            println!("[ERROR] Could not move the block store in {} to {}: {}", BLOCKS_DIR, aside, err);
+            std::process::exit(1);
         }
     }
 }
 
 fn save_app_state(state: &AppState) {
-    // ... (this function is unchanged)
-    let file = File::create(DB_PATH).expect("Could not create state file.");
-    serde_json::to_writer_pretty(file, state).expect("Could not write state to file.");
-    println!("\n[INFO] Application state saved to {}.", DB_PATH);
+    state_file::save(state);
+    println!("\n[INFO] Application state saved to {}.", STATE_PATH);
+}
+
+/// Solo challenges are checked against the registry when they are loaded.
+/// A skill without a registered language can never be earned, which only
+/// deserves a warning.
+fn check_language_registry(languages: &LanguageRegistry) {
+    for skill in Skill::ALL {
+        let name = format!("{:?}", skill);
+        if !languages.contains(&name) {
+            println!("[WARN] Skill {} has no entry in the language registry.", name);
+        }
+    }
 }
 
 #[tokio::main]
@@ -54,18 +155,61 @@ async fn main() {
     println!(" Initializing DevChain Node...");
     println!("========================================");
 
+    let config = match NodeConfig::load() {
+        Ok(config) => config,
+        Err(err) => {
+            println!("[ERROR] Could not read {}: {}", config::CONFIG_PATH, err);
+            std::process::exit(1);
+        }
+    };
+    let languages = match LanguageRegistry::load(config.language_registry.as_deref()) {
+        Ok(languages) => languages,
+        Err(err) => {
+            println!("[ERROR] Could not load the language registry: {}", err);
+            std::process::exit(1);
+        }
+    };
+    check_language_registry(&languages);
+    match definition::load_definitions(config.challenges_dir.as_deref(), &languages) {
+        Ok(definitions) => {
+            println!("[INFO] Loaded {} challenge(s) from {}.", definitions.len(), config.challenges_dir.as_deref().unwrap_or("the built-in set"));
+            let registry = ChallengeRegistry::new(definitions);
+            // Nodes only agree on which challenge comes next if this matches.
+            println!("[INFO] Challenge set fingerprint: {}", registry.fingerprint());
+            registry.install();
+        }
+        Err(err) => {
+            println!("[ERROR] Could not load the challenges: {}", err);
+            std::process::exit(1);
+        }
+    }
+    let store_dir = config.oracle_store_dir.clone()
+        .or_else(|| Path::new(DEFAULT_ORACLE_STORE_DIR).is_dir().then(|| DEFAULT_ORACLE_STORE_DIR.to_string()));
+    let store = match store_dir.as_deref().map(|dir| (dir, OracleStore::load(dir))) {
+        Some((dir, Ok(store))) => {
+            println!("[INFO] Oracle store loaded from {}.", dir);
+            Some(store)
+        }
+        Some((_, Err(err))) => {
+            println!("[ERROR] Could not load the oracle store: {}", err);
+            std::process::exit(1);
+        }
+        None => {
+            println!("[WARN] No oracle store found (set oracle_store_dir, or run from a directory with {}/), so solutions cannot be verified: jobs and attestation requests are refused.", DEFAULT_ORACLE_STORE_DIR);
+            None
+        }
+    };
+    let oracle = Arc::new(VerificationOracle::new(OracleRunner::new(sandbox::backend_for(config.sandbox), config.limits, languages), store));
+    if let Err(err) = oracle.check_challenges(ChallengeRegistry::global()) {
+        println!("[ERROR] The oracle store does not match the challenges: {}", err);
+        std::process::exit(1);
+    }
+
     let app_state = load_app_state();
+    let wallet = Arc::new(Wallet::load_or_create(WALLET_PATH));
     
     // Create a thread-safe, shareable reference to the application state
     let shared_state = Arc::new(Mutex::new(app_state));
-    
-    // Clone the reference for the API server
-    let api_state = shared_state.clone();
-    
-    // Launch the API server in a separate, non-blocking task
-    tokio::spawn(async move {
-        api::start_api_server(api_state).await;
-    });
 
     // Ensure our current developer profile exists in the state
     { // Create a new scope to release the lock quickly
@@ -74,11 +218,50 @@ async fn main() {
             .or_insert_with(|| crate::gamification::profile::DeveloperProfile::new(CURRENT_DEVELOPER_ID));
     }
 
-    let p2p_service = P2PService::new().await;
+    let p2p_service = Arc::new(P2PService::new(wallet.identity()).await);
+    let verifiers = Arc::new(VerifierSet::new(config.verifiers, wallet.address()));
+    let similarity = Arc::new(SimilarityCheck::new(config.similarity));
+    let jobs = Arc::new(JobQueue::start(
+        config.verification_workers,
+        oracle.clone(),
+        shared_state.clone(),
+        p2p_service.clone(),
+        wallet.clone(),
+        verifiers.clone(),
+        similarity.clone(),
+    ));
+
+    // Clone the references for the API server
+    let api_state = api::ApiState {
+        app: shared_state.clone(),
+        wallet: wallet.clone(),
+        jobs: jobs.clone(),
+        oracle: oracle.clone(),
+        verifiers: verifiers.clone(),
+    };
+    
+    // Launch the API server in a separate, non-blocking task
+    tokio::spawn(async move {
+        api::start_api_server(api_state).await;
+    });
+
     println!("\n[INFO] DevChain Node is running for developer: {}", CURRENT_DEVELOPER_ID);
+    println!("[INFO] Block rewards are paid to wallet: {}", wallet.address());
+    println!("[INFO] Solutions are verified in the {} sandbox by {} worker(s).", oracle.backend_name(), config.verification_workers.max(1));
+    println!("[INFO] Blocks need {} verifier attestation(s); {} verifier(s) registered.", verifiers.quorum(), verifiers.registered_count());
+    if verifiers.quorum() > verifiers.registered_count() {
+        println!("[WARN] The verifier quorum is larger than the number of registered verifiers; no block can reach it.");
+    } else if verifiers.quorum() == verifiers.registered_count() {
+        println!("[WARN] A verifier does not count towards blocks that reward it, so blocks paying this node's wallet cannot reach the quorum.");
+    }
+    let copies = match similarity.action() {
+        SimilarityAction::Reject => "refused",
+        SimilarityAction::Flag => "flagged",
+    };
+    println!("[INFO] Solutions {:.0}% similar to an earlier one are {}.", similarity.threshold() * 100.0, copies);
 
     // Run the CLI with its own reference to the state
-    run_cli(shared_state.clone(), CURRENT_DEVELOPER_ID, &p2p_service).await;
+    run_cli(shared_state.clone(), CURRENT_DEVELOPER_ID, &wallet, &oracle, &jobs).await;
 
     // Save the final state when the CLI exits
     save_app_state(&shared_state.lock().unwrap());
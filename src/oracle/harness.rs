@@ -0,0 +1,381 @@
+// Language-neutral test cases. A challenge states the signature of `solve`
+// and a list of input/expected-output pairs; each language's generated
+// driver reads the cases from stdin, calls `solve` and prints one line per
+// case, and the oracle compares those lines here. One suite therefore
+// covers every language in the registry.
+//
+// Only the sample cases are shown to solvers (`SampleTests`). Hidden cases,
+// and inputs generated from a seed the solver cannot know in advance, keep a
+// solution from passing by hard-coding the expected answers; they are kept
+// in the oracle store with the reference solutions.
+use serde::{Serialize, Deserialize};
+use sha2::{Sha256, Digest};
+use std::fmt;
+
+/// The value types a driver knows how to read from and print to a line.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueType {
+    /// A 64-bit signed integer.
+    Int,
+    /// A 64-bit float.
+    Float,
+    /// One line of text.
+    String,
+}
+
+impl ValueType {
+    pub const ALL: [ValueType; 3] = [ValueType::Int, ValueType::Float, ValueType::String];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ValueType::Int => "int",
+            ValueType::Float => "float",
+            ValueType::String => "string",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Param {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub value_type: ValueType,
+}
+
+/// The parameters and return type of the challenge's `solve` function.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Signature {
+    pub params: Vec<Param>,
+    pub returns: ValueType,
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params: Vec<String> = self.params.iter()
+            .map(|param| format!("{}: {}", param.name, param.value_type.as_str()))
+            .collect();
+        write!(f, "solve({}) -> {}", params.join(", "), self.returns.as_str())
+    }
+}
+
+/// One argument per parameter, in order, and the line `solve` must print.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TestCase {
+    pub input: Vec<String>,
+    pub expected: String,
+}
+
+/// How much of a line's whitespace is significant.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Whitespace {
+    /// Lines must match byte for byte.
+    Exact,
+    /// Leading and trailing whitespace is ignored.
+    #[default]
+    Trim,
+    /// Any run of whitespace counts as a single space.
+    Collapse,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct OutputRules {
+    #[serde(default)]
+    pub whitespace: Whitespace,
+    /// Numbers may differ by this much, absolute or relative to the
+    /// expected value, whichever is larger. Exact comparison when unset.
+    #[serde(default)]
+    pub float_tolerance: Option<f64>,
+}
+
+impl OutputRules {
+    fn normalize(&self, line: &str) -> String {
+        match self.whitespace {
+            Whitespace::Exact => line.to_string(),
+            Whitespace::Trim => line.trim().to_string(),
+            Whitespace::Collapse => line.split_whitespace().collect::<Vec<_>>().join(" "),
+        }
+    }
+
+    fn matches(&self, expected: &str, actual: &str) -> bool {
+        let (expected, actual) = (self.normalize(expected), self.normalize(actual));
+        if expected == actual {
+            return true;
+        }
+        let Some(tolerance) = self.float_tolerance else { return false };
+        let (expected, actual): (Vec<&str>, Vec<&str>) = (expected.split_whitespace().collect(), actual.split_whitespace().collect());
+        expected.len() == actual.len() && expected.iter().zip(&actual).all(|(want, got)| {
+            match (want.parse::<f64>(), got.parse::<f64>()) {
+                (Ok(want), Ok(got)) => (want - got).abs() <= tolerance * want.abs().max(1.0),
+                _ => want == got,
+            }
+        })
+    }
+}
+
+/// The values a generated argument is drawn from.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum InputRange {
+    Int { min: i64, max: i64 },
+    Float { min: f64, max: f64 },
+    String { alphabet: String, min_len: usize, max_len: usize },
+}
+
+impl InputRange {
+    fn value_type(&self) -> ValueType {
+        match self {
+            InputRange::Int { .. } => ValueType::Int,
+            InputRange::Float { .. } => ValueType::Float,
+            InputRange::String { .. } => ValueType::String,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let valid = match self {
+            InputRange::Int { min, max } => min <= max,
+            InputRange::Float { min, max } => min.is_finite() && max.is_finite() && min <= max,
+            InputRange::String { alphabet, min_len, max_len } => {
+                !alphabet.is_empty() && !alphabet.contains(['\n', '\r']) && min_len <= max_len
+            }
+        };
+        if valid { Ok(()) } else { Err(format!("invalid generator range {:?}", self)) }
+    }
+
+    fn draw(&self, stream: &mut SeededStream) -> String {
+        match self {
+            InputRange::Int { min, max } => {
+                let span = (*max as i128 - *min as i128 + 1) as u128;
+                (*min as i128 + (stream.next_u64() as u128 % span) as i128).to_string()
+            }
+            InputRange::Float { min, max } => {
+                let fraction = stream.next_u64() as f64 / u64::MAX as f64;
+                (min + fraction * (max - min)).to_string()
+            }
+            InputRange::String { alphabet, min_len, max_len } => {
+                let alphabet: Vec<char> = alphabet.chars().collect();
+                let len = min_len + (stream.next_u64() % (max_len - min_len + 1) as u64) as usize;
+                (0..len).map(|_| alphabet[(stream.next_u64() % alphabet.len() as u64) as usize]).collect()
+            }
+        }
+    }
+}
+
+/// Random cases whose inputs come from a seed and whose expected outputs
+/// come from running the challenge's reference solution on them.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Generator {
+    pub count: usize,
+    /// One range per parameter of `solve`.
+    pub inputs: Vec<InputRange>,
+    /// The language of the reference solution that answers the cases.
+    pub reference_language: String,
+}
+
+impl Generator {
+    /// `count` argument lists, always the same for the same seed.
+    pub fn generate(&self, seed: &str) -> Vec<Vec<String>> {
+        let mut stream = SeededStream { seed: seed.to_string(), counter: 0 };
+        (0..self.count)
+            .map(|_| self.inputs.iter().map(|range| range.draw(&mut stream)).collect())
+            .collect()
+    }
+}
+
+/// Deterministic pseudo-random numbers: SHA-256 of the seed and a counter.
+/// Every node derives the same inputs from the same seed.
+struct SeededStream {
+    seed: String,
+    counter: u64,
+}
+
+impl SeededStream {
+    fn next_u64(&mut self) -> u64 {
+        let digest = Sha256::digest(format!("{}:{}", self.seed, self.counter).as_bytes());
+        self.counter += 1;
+        u64::from_be_bytes(digest[..8].try_into().unwrap())
+    }
+}
+
+/// What solvers see of a suite: the signature, the sample cases and how
+/// output is compared.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SampleTests {
+    pub signature: Signature,
+    pub samples: Vec<TestCase>,
+    #[serde(default)]
+    pub rules: OutputRules,
+}
+
+#[derive(Debug, Clone)]
+pub struct TestSuite {
+    pub signature: Signature,
+    /// Shown to solvers as examples.
+    pub samples: Vec<TestCase>,
+    /// Run by the oracle only; failures do not reveal the case.
+    pub hidden: Vec<TestCase>,
+    pub generator: Option<Generator>,
+    pub rules: OutputRules,
+}
+
+impl TestSuite {
+    pub fn new(tests: &SampleTests, hidden: Vec<TestCase>, generator: Option<Generator>) -> Self {
+        TestSuite {
+            signature: tests.signature.clone(),
+            samples: tests.samples.clone(),
+            hidden,
+            generator,
+            rules: tests.rules.clone(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.samples.is_empty() {
+            return Err("the suite has no sample cases".to_string());
+        }
+        for (number, case) in self.cases().enumerate().map(|(index, case)| (index + 1, case)) {
+            if case.input.len() != self.signature.params.len() {
+                return Err(format!("case {} has {} inputs but solve takes {}", number, case.input.len(), self.signature.params.len()));
+            }
+            if case.input.iter().chain([&case.expected]).any(|value| value.contains(['\n', '\r'])) {
+                return Err(format!("case {} has a value spanning several lines", number));
+            }
+        }
+        if let Some(generator) = &self.generator {
+            if generator.inputs.len() != self.signature.params.len() {
+                return Err(format!("the generator has {} inputs but solve takes {}", generator.inputs.len(), self.signature.params.len()));
+            }
+            for (range, param) in generator.inputs.iter().zip(&self.signature.params) {
+                range.validate()?;
+                if range.value_type() != param.value_type {
+                    return Err(format!("the generator range for {} is not of type {}", param.name, param.value_type.as_str()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Samples first, then hidden cases.
+    pub fn cases(&self) -> impl Iterator<Item = &TestCase> {
+        self.samples.iter().chain(&self.hidden)
+    }
+
+    fn case_count(&self) -> usize {
+        self.samples.len() + self.hidden.len()
+    }
+
+    /// What the driver reads: the number of cases, then each case's
+    /// arguments one per line.
+    pub fn stdin(&self) -> String {
+        let mut input = format!("{}\n", self.case_count());
+        for value in self.cases().flat_map(|case| &case.input) {
+            input.push_str(value);
+            input.push('\n');
+        }
+        input
+    }
+
+    /// The driver's output with trailing blank lines dropped.
+    pub fn answers(stdout: &str) -> Vec<&str> {
+        let mut lines: Vec<&str> = stdout.lines().collect();
+        while lines.last().is_some_and(|line| line.trim().is_empty()) {
+            lines.pop();
+        }
+        lines
+    }
+
+    /// Compares the driver's output with the expected lines, describing
+    /// the first case that does not match. A hidden case is only named.
+    pub fn check(&self, stdout: &str) -> Result<(), String> {
+        let lines = Self::answers(stdout);
+        for (index, case) in self.cases().enumerate() {
+            let name = match index.checked_sub(self.samples.len()) {
+                None => format!("sample case {} of {}", index + 1, self.samples.len()),
+                Some(hidden) => format!("hidden case {} of {}", hidden + 1, self.hidden.len()),
+            };
+            let Some(actual) = lines.get(index) else {
+                return Err(format!("{}: no output", name));
+            };
+            if self.rules.matches(&case.expected, actual) {
+                continue;
+            }
+            return Err(if index < self.samples.len() {
+                format!("{}: expected `{}`, got `{}`", name, case.expected, actual)
+            } else {
+                format!("{}: wrong answer", name)
+            });
+        }
+        if lines.len() > self.case_count() {
+            return Err(format!("printed {} lines for {} cases; stdout is reserved for answers", lines.len(), self.case_count()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suite(rules: &str) -> TestSuite {
+        let tests: SampleTests = serde_json::from_str(&format!(r#"{{
+            "signature": {{ "params": [{{ "name": "n", "type": "int" }}], "returns": "int" }},
+            "samples": [{{ "input": ["1"], "expected": "1" }}, {{ "input": ["2"], "expected": "4" }}],
+            "rules": {}
+        }}"#, rules)).unwrap();
+        let hidden = vec![TestCase { input: vec!["3".to_string()], expected: "9".to_string() }];
+        TestSuite::new(&tests, hidden, None)
+    }
+
+    #[test]
+    fn stdin_lists_the_case_count_then_every_argument() {
+        assert_eq!(suite("{}").stdin(), "3\n1\n2\n3\n");
+    }
+
+    #[test]
+    fn check_accepts_matching_answers_and_trailing_blank_lines() {
+        assert_eq!(suite("{}").check("1\n 4 \n9\n\n\n"), Ok(()));
+    }
+
+    #[test]
+    fn sample_mismatch_shows_the_expected_and_actual_lines() {
+        assert_eq!(suite("{}").check("1\n5\n9\n").unwrap_err(), "sample case 2 of 2: expected `4`, got `5`");
+    }
+
+    #[test]
+    fn hidden_mismatch_does_not_reveal_the_case() {
+        let err = suite("{}").check("1\n4\n10\n").unwrap_err();
+        assert_eq!(err, "hidden case 1 of 1: wrong answer");
+        assert!(!err.contains('9'));
+    }
+
+    #[test]
+    fn missing_and_extra_output_are_failures() {
+        assert_eq!(suite("{}").check("1\n4\n").unwrap_err(), "hidden case 1 of 1: no output");
+        assert_eq!(
+            suite("{}").check("1\n4\n9\ndebug\n").unwrap_err(),
+            "printed 4 lines for 3 cases; stdout is reserved for answers",
+        );
+    }
+
+    #[test]
+    fn rules_control_whitespace_and_float_tolerance() {
+        assert!(suite(r#"{ "whitespace": "exact" }"#).check("1\n4 \n9\n").is_err());
+        assert!(suite(r#"{ "float_tolerance": 0.01 }"#).check("1.001\n4\n9\n").is_ok());
+        assert!(suite("{}").check("1.001\n4\n9\n").is_err());
+    }
+
+    #[test]
+    fn generator_is_deterministic_per_seed_and_stays_in_range() {
+        let generator = Generator {
+            count: 20,
+            inputs: vec![InputRange::Int { min: -3, max: 3 }],
+            reference_language: "Python".to_string(),
+        };
+        let cases = generator.generate("block-1");
+        assert_eq!(cases, generator.generate("block-1"));
+        assert_ne!(cases, generator.generate("block-2"));
+        assert!(cases.iter().all(|case| (-3..=3).contains(&case[0].parse::<i64>().unwrap())));
+    }
+}
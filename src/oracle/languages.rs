@@ -0,0 +1,189 @@
+// The language registry: how each supported language is built and tested,
+// including the template of its test driver (see `harness`). It is read
+// from a JSON file (`languages.json` ships with the node and is also the
+// built-in default), so languages can be added or re-pinned without
+// recompiling.
+//
+// Container backends build one runner image per language from `base_image`
+// plus `setup`, and mount each job's files into it at run time. The
+// local-process backend runs `build` and `run` directly on the host.
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use crate::oracle::harness::{Signature, ValueType};
+use crate::oracle::limits::LimitOverrides;
+
+const BUILT_IN_REGISTRY: &str = include_str!("../../languages.json");
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LanguageSpec {
+    pub base_image: String,
+    /// Extra Dockerfile instructions for the runner image, e.g. installing a compiler.
+    #[serde(default)]
+    pub setup: Vec<String>,
+    pub source_file: String,
+    /// Where the test driver is written. Left out when `merge_tests` is set.
+    #[serde(default)]
+    pub test_file: Option<String>,
+    /// Append the test driver to the solution in `source_file` instead.
+    #[serde(default)]
+    pub merge_tests: bool,
+    /// Compiles the job in its directory. Empty for interpreted languages.
+    #[serde(default)]
+    pub build: Vec<String>,
+    /// Runs the test driver in the job directory, with the cases on stdin.
+    pub run: Vec<String>,
+    pub driver: DriverTemplate,
+    /// Whether `build` and `run` rely on files only the runner image has.
+    #[serde(default)]
+    pub needs_container: bool,
+    /// Overrides the node's default resource limits for this language.
+    #[serde(default)]
+    pub limits: LimitOverrides,
+}
+
+impl LanguageSpec {
+    /// The Dockerfile of this language's runner image.
+    pub fn toolchain(&self) -> String {
+        let mut dockerfile = format!("FROM {}\n", self.base_image);
+        for line in &self.setup {
+            dockerfile.push_str(line);
+            dockerfile.push('\n');
+        }
+        dockerfile
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        self.driver.validate()?;
+        if self.base_image.trim().is_empty() {
+            return Err("base_image is empty".to_string());
+        }
+        if self.run.is_empty() {
+            return Err("run has no command".to_string());
+        }
+        let is_plain_name = |name: &str| !name.is_empty() && !name.contains(['/', '\\']) && name != "." && name != "..";
+        if !is_plain_name(&self.source_file) {
+            return Err(format!("source_file {:?} must be a plain file name", self.source_file));
+        }
+        match (&self.test_file, self.merge_tests) {
+            (Some(_), true) => Err("test_file cannot be set together with merge_tests".to_string()),
+            (None, false) => Err("either test_file or merge_tests must be set".to_string()),
+            (Some(test_file), false) if !is_plain_name(test_file) || *test_file == self.source_file => {
+                Err(format!("test_file {:?} must be a plain file name other than source_file", test_file))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How to read and print one value type, e.g. `{{var}} = int(next(lines))`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TypeTemplate {
+    /// The type's name in this language, shown to solvers.
+    pub name: String,
+    /// A statement declaring `{{var}}` from the next input line.
+    pub read: String,
+    /// A statement printing `{{value}}` on its own line.
+    pub write: String,
+}
+
+/// The test driver of a language. `program` reads the case count from
+/// stdin and loops over the cases; the lines `{{read_args}}` and
+/// `{{write_result}}` inside the loop are replaced by the reads of each
+/// argument and the printing of `call`, keeping their indentation.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DriverTemplate {
+    pub program: String,
+    /// How the driver calls the solution, with `{{args}}` for the arguments.
+    pub call: String,
+    pub types: BTreeMap<ValueType, TypeTemplate>,
+}
+
+impl DriverTemplate {
+    /// The driver for a `solve` with this signature.
+    pub fn render(&self, signature: &Signature) -> String {
+        let args: Vec<String> = (0..signature.params.len()).map(|index| format!("arg{}", index)).collect();
+        let reads: Vec<String> = signature.params.iter().zip(&args)
+            .map(|(param, arg)| self.types[&param.value_type].read.replace("{{var}}", arg))
+            .collect();
+        let call = self.call.replace("{{args}}", &args.join(", "));
+        let write = self.types[&signature.returns].write.replace("{{value}}", &call);
+
+        let mut driver = String::new();
+        for line in self.program.lines() {
+            let indent = &line[..line.len() - line.trim_start().len()];
+            let statements = match line.trim() {
+                "{{read_args}}" => reads.clone(),
+                "{{write_result}}" => vec![write.clone()],
+                _ => vec![line.trim_start().to_string()],
+            };
+            for statement in statements {
+                driver.push_str(indent);
+                driver.push_str(&statement);
+                driver.push('\n');
+            }
+        }
+        driver
+    }
+
+    /// `name` of each parameter type, as the solution must declare them.
+    pub fn describe(&self, signature: &Signature) -> String {
+        let params: Vec<String> = signature.params.iter()
+            .map(|param| format!("{}: {}", param.name, self.types[&param.value_type].name))
+            .collect();
+        format!("solve({}) -> {}", params.join(", "), self.types[&signature.returns].name)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for placeholder in ["{{read_args}}", "{{write_result}}"] {
+            if !self.program.lines().any(|line| line.trim() == placeholder) {
+                return Err(format!("driver program needs a line with just {}", placeholder));
+            }
+        }
+        if !self.call.contains("{{args}}") {
+            return Err("driver call must contain {{args}}".to_string());
+        }
+        for value_type in ValueType::ALL {
+            let Some(template) = self.types.get(&value_type) else {
+                return Err(format!("driver has no template for {}", value_type.as_str()));
+            };
+            if !template.read.contains("{{var}}") || !template.write.contains("{{value}}") {
+                return Err(format!("driver template for {} must use {{{{var}}}} and {{{{value}}}}", value_type.as_str()));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LanguageRegistry {
+    languages: BTreeMap<String, LanguageSpec>,
+}
+
+impl LanguageRegistry {
+    /// Reads the registry at `path`, or the built-in one when no path is configured.
+    pub fn load(path: Option<&str>) -> Result<Self, String> {
+        let contents = match path {
+            Some(path) => fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?,
+            None => BUILT_IN_REGISTRY.to_string(),
+        };
+        let registry: LanguageRegistry = serde_json::from_str(&contents)
+            .map_err(|err| format!("{}: {}", path.unwrap_or("built-in language registry"), err))?;
+        for (name, spec) in &registry.languages {
+            spec.validate().map_err(|reason| format!("language {}: {}", name, reason))?;
+        }
+        Ok(registry)
+    }
+
+    pub fn get(&self, language: &str) -> Option<&LanguageSpec> {
+        self.languages.get(language)
+    }
+
+    pub fn contains(&self, language: &str) -> bool {
+        self.languages.contains_key(language)
+    }
+}
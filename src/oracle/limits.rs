@@ -0,0 +1,77 @@
+// Resource limits for running untrusted solutions. The language registry
+// sets per-language limits; the `limits` section of the node config sets the
+// defaults and can override any language.
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct ResourceLimits {
+    /// Wall-clock time for compiling the solution.
+    pub build_timeout_secs: u64,
+    /// Wall-clock time for running the tests; the container is killed after it.
+    pub timeout_secs: u64,
+    pub memory_mb: u64,
+    pub cpus: f64,
+    /// Most processes and threads the solution may have at once.
+    pub pids: u64,
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        ResourceLimits { build_timeout_secs: 300, timeout_secs: 10, memory_mb: 512, cpus: 1.0, pids: 64 }
+    }
+}
+
+impl ResourceLimits {
+    pub fn build_timeout(&self) -> Duration {
+        Duration::from_secs(self.build_timeout_secs)
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+/// Fields left out fall back to the limits underneath.
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitOverrides {
+    pub build_timeout_secs: Option<u64>,
+    pub timeout_secs: Option<u64>,
+    pub memory_mb: Option<u64>,
+    pub cpus: Option<f64>,
+    pub pids: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct LimitsConfig {
+    pub default: ResourceLimits,
+    pub languages: HashMap<String, LimitOverrides>,
+}
+
+impl LimitOverrides {
+    fn apply_to(&self, base: ResourceLimits) -> ResourceLimits {
+        ResourceLimits {
+            build_timeout_secs: self.build_timeout_secs.unwrap_or(base.build_timeout_secs),
+            timeout_secs: self.timeout_secs.unwrap_or(base.timeout_secs),
+            memory_mb: self.memory_mb.unwrap_or(base.memory_mb),
+            cpus: self.cpus.unwrap_or(base.cpus),
+            pids: self.pids.unwrap_or(base.pids),
+        }
+    }
+}
+
+impl LimitsConfig {
+    /// `default`, then the language registry's limits for `language`, then
+    /// this node's own overrides for it.
+    pub fn for_language(&self, language: &str, registry_limits: &LimitOverrides) -> ResourceLimits {
+        let limits = registry_limits.apply_to(self.default);
+        match self.languages.get(language) {
+            Some(overrides) => overrides.apply_to(limits),
+            None => limits,
+        }
+    }
+}
@@ -1 +1,6 @@
-pub mod docker_verifier;
\ No newline at end of file
+pub mod harness;
+pub mod languages;
+pub mod limits;
+pub mod report;
+pub mod runner;
+pub mod sandbox;
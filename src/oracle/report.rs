@@ -0,0 +1,128 @@
+// What the oracle found out about a submitted solution.
+use sha2::{Sha256, Digest};
+use std::fmt;
+use std::time::Duration;
+use crate::core_types::encoding::Encoder;
+
+/// What a report holds in place of the output of a run with hidden cases.
+const OUTPUT_WITHHELD: &str = "(output withheld: the run included hidden cases)\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Passed,
+    WrongAnswer,
+    CompileError,
+    /// The build or the test run went past its time limit.
+    Timeout,
+    OutOfMemory,
+    /// The solution tried to start more processes or threads than allowed.
+    ProcessLimit,
+    /// The build or the test run printed more than the sandbox keeps.
+    OutputLimit,
+    /// The sandbox failed, not the solution. Never held against the author.
+    InfraError,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Passed => write!(f, "tests passed"),
+            Verdict::WrongAnswer => write!(f, "tests failed"),
+            Verdict::CompileError => write!(f, "solution did not compile"),
+            Verdict::Timeout => write!(f, "time limit exceeded"),
+            Verdict::OutOfMemory => write!(f, "memory limit exceeded"),
+            Verdict::ProcessLimit => write!(f, "process limit exceeded"),
+            Verdict::OutputLimit => write!(f, "output limit exceeded"),
+            Verdict::InfraError => write!(f, "verification could not run"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VerificationReport {
+    pub verdict: Verdict,
+    /// Output of the test run, or of the build if it never got that far.
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: Option<i32>,
+    /// `None` when there was no separate build step.
+    pub build_duration: Option<Duration>,
+    /// `None` when the tests never ran.
+    pub run_duration: Option<Duration>,
+    /// The image the tests ran in, for container backends.
+    pub image_digest: Option<String>,
+    /// For a wrong answer, the first test case that did not match.
+    pub mismatch: Option<String>,
+}
+
+impl VerificationReport {
+    pub fn new(verdict: Verdict) -> Self {
+        VerificationReport {
+            verdict,
+            stdout: String::new(),
+            stderr: String::new(),
+            exit_code: None,
+            build_duration: None,
+            run_duration: None,
+            image_digest: None,
+            mismatch: None,
+        }
+    }
+
+    pub fn infra_error(message: impl fmt::Display) -> Self {
+        let mut report = VerificationReport::new(Verdict::InfraError);
+        report.stderr = message.to_string();
+        report
+    }
+
+    pub fn passed(&self) -> bool {
+        self.verdict == Verdict::Passed
+    }
+
+    /// Drops everything the run printed except the answers to the first
+    /// `samples` cases. Backend failures are kept, since they come from the
+    /// node rather than the solution.
+    pub fn withhold_output(&mut self, samples: usize) {
+        if self.verdict == Verdict::InfraError {
+            return;
+        }
+        self.stdout = self.stdout.lines().take(samples).map(|line| format!("{}\n", line)).collect();
+        self.stderr = OUTPUT_WITHHELD.to_string();
+    }
+
+    /// SHA-256 over what the run produced, leaving out timings. Signed into
+    /// attestations so a verifier can later show which report it vouched for.
+    pub fn digest(&self) -> String {
+        let mut enc = Encoder::new();
+        enc.str(&format!("{:?}", self.verdict))
+            .str(&self.exit_code.map(|code| code.to_string()).unwrap_or_default())
+            .str(&self.stdout)
+            .str(&self.stderr)
+            .str(self.image_digest.as_deref().unwrap_or_default())
+            .str(self.mismatch.as_deref().unwrap_or_default());
+        format!("{:x}", Sha256::digest(enc.finish()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn withheld_output_keeps_only_the_sample_answers() {
+        let mut report = VerificationReport::new(Verdict::WrongAnswer);
+        report.stdout = "1\n4\n9\n".to_string();
+        report.stderr = "expected 9 for n = 3\n".to_string();
+        report.withhold_output(2);
+        assert_eq!(report.stdout, "1\n4\n");
+        assert_eq!(report.stderr, OUTPUT_WITHHELD);
+    }
+
+    #[test]
+    fn infrastructure_errors_are_not_withheld() {
+        let mut report = VerificationReport::infra_error("docker is not running");
+        let before = report.stderr.clone();
+        report.withhold_output(0);
+        assert_eq!(report.stderr, before);
+    }
+}
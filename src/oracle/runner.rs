@@ -0,0 +1,171 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use tempfile::Builder;
+use crate::oracle::harness::{Signature, TestCase, TestSuite};
+use crate::oracle::languages::{LanguageRegistry, LanguageSpec};
+use crate::oracle::limits::LimitsConfig;
+use crate::oracle::report::{Verdict, VerificationReport};
+use crate::oracle::sandbox::{SandboxBackend, STDIN_FILE};
+
+pub struct OracleRunner {
+    backend: Box<dyn SandboxBackend>,
+    limits: LimitsConfig,
+    languages: LanguageRegistry,
+}
+
+impl OracleRunner {
+    pub fn new(backend: Box<dyn SandboxBackend>, limits: LimitsConfig, languages: LanguageRegistry) -> Self {
+        OracleRunner { backend, limits, languages }
+    }
+
+    pub fn backend_name(&self) -> &str {
+        self.backend.name()
+    }
+
+    /// The signature of `solve` in `language`'s own types.
+    pub fn describe(&self, language: &str, signature: &Signature) -> Option<String> {
+        self.languages.get(language).map(|spec| spec.driver.describe(signature))
+    }
+
+    /// Runs `suite` against the solution through the language's generated
+    /// driver and compares what it prints with the expected answers. When
+    /// the suite has hidden cases the run is not echoed, and the report only
+    /// keeps the answers to the samples, since a solution could print the
+    /// hidden inputs.
+    pub fn verify(&self, language: &str, solution_code: &str, suite: &TestSuite) -> VerificationReport {
+        let has_hidden = !suite.hidden.is_empty();
+        let mut report = self.execute(language, solution_code, suite, !has_hidden);
+        if report.passed()
+            && let Err(mismatch) = suite.check(&report.stdout)
+        {
+            report.verdict = Verdict::WrongAnswer;
+            report.mismatch = Some(mismatch);
+        }
+        if has_hidden {
+            report.withhold_output(suite.samples.len());
+        }
+        report
+    }
+
+    /// Runs a reference solution on `inputs` and turns its answers into
+    /// test cases.
+    pub fn answer(&self, language: &str, reference_code: &str, suite: &TestSuite, inputs: Vec<Vec<String>>) -> Result<Vec<TestCase>, String> {
+        let questions = TestSuite {
+            signature: suite.signature.clone(),
+            samples: inputs.into_iter().map(|input| TestCase { input, expected: String::new() }).collect(),
+            hidden: Vec::new(),
+            generator: None,
+            rules: suite.rules.clone(),
+        };
+        // The answers become hidden cases, so they are not echoed either.
+        let report = self.execute(language, reference_code, &questions, false);
+        if !report.passed() {
+            return Err(format!("the {} reference solution failed: {} {}", language, report.verdict, report.stderr.trim_end()));
+        }
+        let answers = TestSuite::answers(&report.stdout);
+        if answers.len() != questions.samples.len() {
+            return Err(format!("the {} reference solution printed {} answers for {} cases", language, answers.len(), questions.samples.len()));
+        }
+        Ok(questions.samples.into_iter().zip(answers)
+            .map(|(case, answer)| TestCase { input: case.input, expected: answer.to_string() })
+            .collect())
+    }
+
+    /// Builds and runs the job without looking at its output.
+    fn execute(&self, language: &str, solution_code: &str, suite: &TestSuite, echo: bool) -> VerificationReport {
+        let Some(spec) = self.languages.get(language) else {
+            return VerificationReport::infra_error(format!("unsupported language for verification: {}", language));
+        };
+        if let Err(reason) = suite.validate() {
+            return VerificationReport::infra_error(format!("invalid test suite: {}", reason));
+        }
+
+        let limits = self.limits.for_language(language, &spec.limits);
+        let temp_dir = match Builder::new().prefix("devchain-oracle").tempdir() {
+            Ok(temp_dir) => temp_dir,
+            Err(err) => return VerificationReport::infra_error(format!("could not create a job directory: {}", err)),
+        };
+        if let Err(err) = Self::write_job(spec, temp_dir.path(), solution_code, suite) {
+            return VerificationReport::infra_error(format!("could not write the job files: {}", err));
+        }
+        self.backend.run(language, spec, temp_dir.path(), &limits, echo)
+    }
+
+    fn write_job(spec: &LanguageSpec, dir: &Path, solution_code: &str, suite: &TestSuite) -> io::Result<()> {
+        fs::write(dir.join(STDIN_FILE), suite.stdin())?;
+        let test_code = spec.driver.render(&suite.signature);
+        match &spec.test_file {
+            Some(test_file) => {
+                fs::write(dir.join(&spec.source_file), solution_code)?;
+                fs::write(dir.join(test_file), test_code)
+            }
+            // For compiled languages where test is part of the binary, we combine them
+            None => fs::write(dir.join(&spec.source_file), format!("{}\n\n{}", solution_code, test_code)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oracle::harness::SampleTests;
+    use crate::oracle::sandbox::LocalProcessBackend;
+
+    // Runs Python on the host through the local-process backend, so these
+    // need `python3` but no container runtime.
+    fn runner() -> OracleRunner {
+        OracleRunner::new(Box::new(LocalProcessBackend), LimitsConfig::default(), LanguageRegistry::load(None).unwrap())
+    }
+
+    fn squares(hidden: Vec<TestCase>) -> TestSuite {
+        let tests: SampleTests = serde_json::from_str(r#"{
+            "signature": { "params": [{ "name": "n", "type": "int" }], "returns": "int" },
+            "samples": [{ "input": ["2"], "expected": "4" }, { "input": ["3"], "expected": "9" }]
+        }"#).unwrap();
+        TestSuite::new(&tests, hidden, None)
+    }
+
+    fn case(input: &str, expected: &str) -> TestCase {
+        TestCase { input: vec![input.to_string()], expected: expected.to_string() }
+    }
+
+    #[test]
+    fn correct_solution_passes() {
+        let report = runner().verify("Python", "def solve(n):\n    return n * n\n", &squares(vec![]));
+        assert_eq!(report.verdict, Verdict::Passed, "{}", report.stderr);
+        assert_eq!(report.stdout, "4\n9\n");
+    }
+
+    #[test]
+    fn wrong_answer_names_the_sample() {
+        let report = runner().verify("Python", "def solve(n):\n    return n + n\n", &squares(vec![]));
+        assert_eq!(report.verdict, Verdict::WrongAnswer);
+        assert_eq!(report.mismatch.as_deref(), Some("sample case 2 of 2: expected `9`, got `6`"));
+    }
+
+    #[test]
+    fn output_of_runs_with_hidden_cases_is_withheld() {
+        let leaky = "import sys\ndef solve(n):\n    print('saw', n, file=sys.stderr)\n    return n * n if n < 5 else 0\n";
+        let report = runner().verify("Python", leaky, &squares(vec![case("7", "49")]));
+        assert_eq!(report.verdict, Verdict::WrongAnswer);
+        assert_eq!(report.mismatch.as_deref(), Some("hidden case 1 of 1: wrong answer"));
+        assert_eq!(report.stdout, "4\n9\n");
+        assert!(!report.stderr.contains('7'));
+    }
+
+    #[test]
+    fn solution_printing_without_end_hits_the_output_limit() {
+        let chatty = "def solve(n):\n    while True:\n        print('x' * 1000)\n";
+        let report = runner().verify("Python", chatty, &squares(vec![]));
+        assert_eq!(report.verdict, Verdict::OutputLimit, "{}", report.stderr);
+    }
+
+    #[test]
+    fn reference_solution_answers_generated_inputs() {
+        let inputs = vec![vec!["5".to_string()], vec!["-4".to_string()]];
+        let answers = runner().answer("Python", "def solve(n):\n    return n * n\n", &squares(vec![]), inputs).unwrap();
+        let answers: Vec<(&str, &str)> = answers.iter().map(|case| (case.input[0].as_str(), case.expected.as_str())).collect();
+        assert_eq!(answers, [("5", "25"), ("-4", "16")]);
+    }
+}
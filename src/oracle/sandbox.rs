@@ -0,0 +1,495 @@
+// Where the oracle runs untrusted solutions. The backend is chosen by the
+// `sandbox` setting in the node config.
+use serde::Deserialize;
+use sha2::{Sha256, Digest};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+use crate::core_types::hex;
+use crate::oracle::languages::LanguageSpec;
+use crate::oracle::limits::ResourceLimits;
+use crate::oracle::report::{Verdict, VerificationReport};
+
+/// The uid:gid solutions run as inside a container ("nobody").
+const SANDBOX_USER: &str = "65534:65534";
+/// Runner images are named `<prefix>-<language>:<toolchain hash>`.
+const RUNNER_IMAGE_PREFIX: &str = "devchain-runner";
+/// The file in the job directory that the test run reads on stdin.
+pub const STDIN_FILE: &str = "cases.in";
+/// How much of each output stream is kept. A run that prints more is
+/// stopped, so a solution printing in a loop cannot fill the host's memory.
+const MAX_OUTPUT_BYTES: usize = 4 * 1024 * 1024;
+/// Exit code of a process killed by SIGKILL, which is how the kernel ends an
+/// out-of-memory container.
+const SIGKILL_EXIT_CODE: i32 = 137;
+/// What runtimes print when they cannot start another process or thread.
+const PROCESS_LIMIT_MARKERS: [&str; 4] = [
+    "Resource temporarily unavailable",
+    "can't start new thread",
+    "unable to create native thread",
+    "fork: retry",
+];
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SandboxKind {
+    #[default]
+    Docker,
+    Podman,
+    /// Runs the toolchain straight on the host, with no isolation at all.
+    /// Meant for CI and development machines without a container runtime.
+    LocalProcess,
+}
+
+pub trait SandboxBackend: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Builds and runs the job prepared in `workdir` within `limits`, with
+    /// `STDIN_FILE` on the run's stdin. Output is echoed to the console as
+    /// it arrives only when `echo` is set. A failure of the backend itself
+    /// comes back as `Verdict::InfraError`.
+    fn run(&self, language: &str, spec: &LanguageSpec, workdir: &Path, limits: &ResourceLimits, echo: bool) -> VerificationReport;
+}
+
+pub fn backend_for(kind: SandboxKind) -> Box<dyn SandboxBackend> {
+    match kind {
+        SandboxKind::Docker => Box::new(ContainerBackend::new("docker")),
+        SandboxKind::Podman => Box::new(ContainerBackend::new("podman")),
+        SandboxKind::LocalProcess => Box::new(LocalProcessBackend),
+    }
+}
+
+/// A child process that has exited or been killed, with its output.
+struct Finished {
+    /// `None` when the process was killed, for running past its time limit
+    /// or for printing too much.
+    status: Option<ExitStatus>,
+    stdout: String,
+    stderr: String,
+    /// Either stream went past `MAX_OUTPUT_BYTES`; the rest was dropped.
+    output_limit_hit: bool,
+    elapsed: Duration,
+}
+
+impl Finished {
+    fn hit_process_limit(&self) -> bool {
+        PROCESS_LIMIT_MARKERS.iter().any(|marker| self.stderr.contains(marker))
+    }
+
+    /// The verdict for a build step that did not succeed.
+    fn build_failure(&self) -> Option<Verdict> {
+        match self.status {
+            _ if self.output_limit_hit => Some(Verdict::OutputLimit),
+            None => Some(Verdict::Timeout),
+            Some(status) if status.success() => None,
+            Some(_) => Some(Verdict::CompileError),
+        }
+    }
+
+    fn record(self, report: &mut VerificationReport) {
+        report.exit_code = self.status.and_then(|status| status.code());
+        report.stdout = self.stdout;
+        report.stderr = self.stderr;
+    }
+}
+
+/// Keeps a copy of the first `MAX_OUTPUT_BYTES` of a child's output stream,
+/// echoing it as it arrives when `echo` is set. Past that, `overflowed` is
+/// set and the stream is closed, so further writes fail. Bytes that are not
+/// UTF-8 are kept as replacement characters.
+fn capture(stream: impl Read + Send + 'static, echo: bool, to_stderr: bool, overflowed: Arc<AtomicBool>) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(stream);
+        let mut captured = Vec::new();
+        loop {
+            let start = captured.len();
+            let room = (MAX_OUTPUT_BYTES - start) as u64;
+            match (&mut reader).take(room + 1).read_until(b'\n', &mut captured) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            if captured.len() > MAX_OUTPUT_BYTES {
+                captured.truncate(MAX_OUTPUT_BYTES);
+                overflowed.store(true, Ordering::Relaxed);
+                break;
+            }
+            if echo {
+                let chunk = String::from_utf8_lossy(&captured[start..]);
+                if to_stderr { eprint!("{}", chunk) } else { print!("{}", chunk) }
+            }
+        }
+        String::from_utf8_lossy(&captured).into_owned()
+    })
+}
+
+/// Runs `command` on `stdin`, capturing its output. If it is still running
+/// after `timeout`, or prints more than `MAX_OUTPUT_BYTES` on either stream,
+/// `kill` is called and it is reaped.
+fn run_with_timeout(command: &mut Command, stdin: Stdio, timeout: Duration, echo: bool, kill: impl FnOnce(&mut Child)) -> io::Result<Finished> {
+    let started = Instant::now();
+    let mut child = command.stdin(stdin).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let overflowed = Arc::new(AtomicBool::new(false));
+    let stdout = child.stdout.take().map(|stream| capture(stream, echo, false, overflowed.clone()));
+    let stderr = child.stderr.take().map(|stream| capture(stream, echo, true, overflowed.clone()));
+
+    let deadline = started + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if Instant::now() >= deadline || overflowed.load(Ordering::Relaxed) {
+            kill(&mut child);
+            child.wait()?;
+            break None;
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+    let elapsed = started.elapsed();
+    let collect = |reader: Option<JoinHandle<String>>| reader.and_then(|reader| reader.join().ok()).unwrap_or_default();
+    let (stdout, stderr) = (collect(stdout), collect(stderr));
+    Ok(Finished { status, stdout, stderr, output_limit_hit: overflowed.load(Ordering::Relaxed), elapsed })
+}
+
+/// Where a runner image stands. Images missing from the map have not been
+/// looked at yet.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ImageState {
+    /// A run is looking the image up or building it; others wait for it.
+    Preparing,
+    Ready,
+}
+
+/// Docker and Podman share a CLI, so one backend drives either.
+pub struct ContainerBackend {
+    program: &'static str,
+    /// Runner images known to exist or being prepared, so they are not
+    /// looked up every run and each is built once. The lock is only held to
+    /// read or change the map, never during a build.
+    images: Mutex<HashMap<String, ImageState>>,
+    /// Signalled whenever an image stops being `Preparing`.
+    image_prepared: Condvar,
+}
+
+impl ContainerBackend {
+    pub fn new(program: &'static str) -> Self {
+        ContainerBackend { program, images: Mutex::new(HashMap::new()), image_prepared: Condvar::new() }
+    }
+
+    fn command(&self) -> Command {
+        Command::new(self.program)
+    }
+
+    /// Fails if the runtime's daemon is not reachable, so that is not
+    /// mistaken for a build failure.
+    fn check_daemon(&self) -> Result<(), String> {
+        let output = self.command().arg("version").output()
+            .map_err(|err| format!("could not run `{}`: {}", self.program, err))?;
+        if !output.status.success() {
+            return Err(format!("{} is not available: {}", self.program, String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        Ok(())
+    }
+
+    fn image_digest(&self, image_tag: &str) -> Option<String> {
+        let output = self.command().args(["image", "inspect", "--format", "{{.Id}}", image_tag]).output().ok()?;
+        let digest = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !digest.is_empty()).then_some(digest)
+    }
+
+    /// Returns the tag of the runner image for `spec`, building it first if
+    /// this toolchain definition has never been built. Runs that need an
+    /// image another run is preparing wait for it; runs of other languages
+    /// go ahead.
+    fn ensure_image(&self, language: &str, spec: &LanguageSpec) -> Result<String, String> {
+        let repository = format!("{}-{}", RUNNER_IMAGE_PREFIX, language.to_lowercase());
+        let toolchain = spec.toolchain();
+        let definition_hash = hex::encode(&Sha256::digest(toolchain.as_bytes()));
+        let image_tag = format!("{}:{}", repository, &definition_hash[..16]);
+
+        let lock = || self.images.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut images = lock();
+        loop {
+            match images.get(&image_tag) {
+                Some(ImageState::Ready) => return Ok(image_tag),
+                Some(ImageState::Preparing) => {
+                    images = self.image_prepared.wait(images).unwrap_or_else(|poisoned| poisoned.into_inner());
+                }
+                None => break,
+            }
+        }
+        images.insert(image_tag.clone(), ImageState::Preparing);
+        drop(images);
+
+        let prepared = self.prepare_image(language, &repository, &image_tag, &toolchain);
+        let mut images = lock();
+        match prepared {
+            Ok(()) => images.insert(image_tag.clone(), ImageState::Ready),
+            Err(_) => images.remove(&image_tag),
+        };
+        self.image_prepared.notify_all();
+        prepared.map(|()| image_tag)
+    }
+
+    /// Builds `image_tag` from `toolchain` unless it already exists. Images
+    /// from older definitions of the same language are removed once the new
+    /// one exists.
+    fn prepare_image(&self, language: &str, repository: &str, image_tag: &str, toolchain: &str) -> Result<(), String> {
+        if self.image_digest(image_tag).is_some() {
+            return Ok(());
+        }
+
+        println!("[ORACLE] Building the {} runner image {} (first use of this toolchain)...", language, image_tag);
+        let mut build = self.command();
+        build.args(["build", "-t", image_tag, "-"]).stdin(Stdio::piped());
+        let mut child = build.spawn().map_err(|err| format!("could not run `{} build`: {}", self.program, err))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(toolchain.as_bytes()).map_err(|err| format!("could not send the Dockerfile: {}", err))?;
+        }
+        let status = child.wait().map_err(|err| err.to_string())?;
+        if !status.success() {
+            return Err(format!("building the {} runner image failed", language));
+        }
+
+        let stale = self.command()
+            .args(["images", repository, "--format", "{{.Repository}}:{{.Tag}}"])
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+            .unwrap_or_default();
+        for old_tag in stale.lines().filter(|tag| *tag != image_tag) {
+            let _ = self.command().args(["rmi", old_tag]).output();
+        }
+        Ok(())
+    }
+
+    /// `run` arguments shared by the build and test steps: the job directory
+    /// mounted at `/job`, no network, a read-only root, an unprivileged user
+    /// and the resource caps.
+    fn sandboxed_run(&self, container: &str, workdir: &Path, writable: bool, limits: &ResourceLimits) -> Command {
+        let mount = format!("{}:/job{}", workdir.display(), if writable { "" } else { ":ro" });
+        let memory = format!("{}m", limits.memory_mb);
+        let mut command = self.command();
+        command.arg("run")
+            .args(["--name", container])
+            .args(["--pull", "never"])
+            .args(["--volume", &mount, "--workdir", "/job"])
+            .args(["--network", "none"])
+            .arg("--read-only")
+            .args(["--tmpfs", "/tmp:rw,exec,size=256m", "--env", "HOME=/tmp"])
+            .args(["--memory", &memory, "--memory-swap", &memory])
+            .args(["--cpus", &limits.cpus.to_string()])
+            .args(["--pids-limit", &limits.pids.to_string()])
+            .args(["--user", SANDBOX_USER])
+            .args(["--cap-drop", "ALL"])
+            .args(["--security-opt", "no-new-privileges"]);
+        command
+    }
+
+    /// Runs a prepared `docker run` command, killing the container after
+    /// `timeout`. Also says whether the runtime killed it for memory.
+    fn run_container(&self, mut command: Command, stdin: Stdio, container: &str, timeout: Duration, echo: bool) -> io::Result<(Finished, bool)> {
+        let program = self.program;
+        let finished = run_with_timeout(&mut command, stdin, timeout, echo, |child| {
+            // Killing only the CLI client would leave the container running,
+            // and the client may not exit if the runtime does not answer.
+            let _ = Command::new(program).args(["kill", container]).status();
+            let _ = child.kill();
+        });
+        // Not `--rm`: the container is inspected for an OOM kill first.
+        let oom_killed = self.command()
+            .args(["inspect", "--format", "{{.State.OOMKilled}}", container])
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim() == "true")
+            .unwrap_or(false);
+        let _ = self.command().args(["rm", "--force", container]).output();
+        Ok((finished?, oom_killed))
+    }
+}
+
+impl SandboxBackend for ContainerBackend {
+    fn name(&self) -> &str {
+        self.program
+    }
+
+    fn run(&self, language: &str, spec: &LanguageSpec, workdir: &Path, limits: &ResourceLimits, echo: bool) -> VerificationReport {
+        if let Err(message) = self.check_daemon() {
+            return VerificationReport::infra_error(message);
+        }
+        let image_tag = match self.ensure_image(language, spec) {
+            Ok(image_tag) => image_tag,
+            Err(message) => return VerificationReport::infra_error(message),
+        };
+        // The job runs as an unprivileged user that must be able to write
+        // its build output next to the sources.
+        if let Err(err) = fs::set_permissions(workdir, fs::Permissions::from_mode(0o777)) {
+            return VerificationReport::infra_error(err);
+        }
+
+        let mut report = VerificationReport::new(Verdict::InfraError);
+        report.image_digest = self.image_digest(&image_tag);
+        let job_id = Uuid::new_v4();
+
+        if !spec.build.is_empty() {
+            println!("[ORACLE] Compiling the solution in {}...", image_tag);
+            let container = format!("devchain-build-{}", job_id);
+            let mut command = self.sandboxed_run(&container, workdir, true, limits);
+            command.arg(&image_tag).args(&spec.build);
+            let (built, _) = match self.run_container(command, Stdio::null(), &container, limits.build_timeout(), echo) {
+                Ok(result) => result,
+                Err(err) => {
+                    report.stderr = err.to_string();
+                    return report;
+                }
+            };
+            report.build_duration = Some(built.elapsed);
+            if let Some(verdict) = built.build_failure() {
+                report.verdict = verdict;
+                built.record(&mut report);
+                return report;
+            }
+        }
+
+        let cases = match File::open(workdir.join(STDIN_FILE)) {
+            Ok(cases) => cases,
+            Err(err) => return VerificationReport::infra_error(format!("could not open the test cases: {}", err)),
+        };
+        println!("[ORACLE] Running the tests in {}...", image_tag);
+        let container = format!("devchain-run-{}", job_id);
+        let mut command = self.sandboxed_run(&container, workdir, false, limits);
+        command.arg("--interactive").arg(&image_tag).args(&spec.run);
+        let (finished, oom_killed) = match self.run_container(command, cases.into(), &container, limits.timeout(), echo) {
+            Ok(result) => result,
+            Err(err) => {
+                report.stderr = err.to_string();
+                return report;
+            }
+        };
+
+        report.run_duration = Some(finished.elapsed);
+        report.verdict = match finished.status {
+            _ if finished.output_limit_hit => Verdict::OutputLimit,
+            None => Verdict::Timeout,
+            Some(status) if status.success() => Verdict::Passed,
+            Some(status) if oom_killed || status.code() == Some(SIGKILL_EXIT_CODE) => Verdict::OutOfMemory,
+            Some(_) if finished.hit_process_limit() => Verdict::ProcessLimit,
+            Some(_) => Verdict::WrongAnswer,
+        };
+        finished.record(&mut report);
+        report
+    }
+}
+
+/// Enforces only the time limits; memory, process and network limits need
+/// a container runtime.
+pub struct LocalProcessBackend;
+
+impl SandboxBackend for LocalProcessBackend {
+    fn name(&self) -> &str {
+        "local process"
+    }
+
+    fn run(&self, _language: &str, spec: &LanguageSpec, workdir: &Path, limits: &ResourceLimits, echo: bool) -> VerificationReport {
+        if spec.needs_container {
+            return VerificationReport::infra_error("this language cannot run outside a container");
+        }
+        let mut report = VerificationReport::new(Verdict::InfraError);
+
+        if let Some((program, args)) = spec.build.split_first() {
+            println!("[ORACLE] Running `{}` on the host...", spec.build.join(" "));
+            let built = match run_with_timeout(
+                Command::new(program).args(args).current_dir(workdir),
+                Stdio::null(),
+                limits.build_timeout(),
+                echo,
+                |child| { let _ = child.kill(); },
+            ) {
+                Ok(built) => built,
+                Err(err) => return VerificationReport::infra_error(format!("could not run `{}`: {}", program, err)),
+            };
+            report.build_duration = Some(built.elapsed);
+            if let Some(verdict) = built.build_failure() {
+                report.verdict = verdict;
+                built.record(&mut report);
+                return report;
+            }
+        }
+
+        let cases = match File::open(workdir.join(STDIN_FILE)) {
+            Ok(cases) => cases,
+            Err(err) => return VerificationReport::infra_error(format!("could not open the test cases: {}", err)),
+        };
+        let run = &spec.run;
+        println!("[ORACLE] Running `{}` on the host...", run.join(" "));
+        let finished = match run_with_timeout(
+            Command::new(&run[0]).args(&run[1..]).current_dir(workdir),
+            cases.into(),
+            limits.timeout(),
+            echo,
+            |child| { let _ = child.kill(); },
+        ) {
+            Ok(finished) => finished,
+            Err(err) => {
+                report.stderr = format!("could not run `{}`: {}", run[0], err);
+                return report;
+            }
+        };
+        report.run_duration = Some(finished.elapsed);
+        report.verdict = match finished.status {
+            _ if finished.output_limit_hit => Verdict::OutputLimit,
+            None => Verdict::Timeout,
+            Some(status) if status.success() => Verdict::Passed,
+            Some(_) => Verdict::WrongAnswer,
+        };
+        finished.record(&mut report);
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn captured(bytes: Vec<u8>) -> (String, bool) {
+        let overflowed = Arc::new(AtomicBool::new(false));
+        let text = capture(Cursor::new(bytes), false, false, overflowed.clone()).join().unwrap();
+        (text, overflowed.load(Ordering::Relaxed))
+    }
+
+    #[test]
+    fn invalid_utf8_is_replaced_and_reading_goes_on() {
+        let (text, overflowed) = captured(b"ok\n\xffbad\nafter\n".to_vec());
+        assert_eq!(text, "ok\n\u{fffd}bad\nafter\n");
+        assert!(!overflowed);
+    }
+
+    #[test]
+    fn output_past_the_cap_is_dropped_and_flagged() {
+        let (text, overflowed) = captured(vec![b'x'; MAX_OUTPUT_BYTES + 10]);
+        assert_eq!(text.len(), MAX_OUTPUT_BYTES);
+        assert!(overflowed);
+
+        let (text, overflowed) = captured(vec![b'x'; MAX_OUTPUT_BYTES]);
+        assert_eq!(text.len(), MAX_OUTPUT_BYTES);
+        assert!(!overflowed);
+    }
+
+    #[test]
+    fn a_process_printing_without_end_is_stopped_early() {
+        let mut command = Command::new("yes");
+        let finished = run_with_timeout(&mut command, Stdio::null(), Duration::from_secs(60), false, |child| {
+            let _ = child.kill();
+        }).unwrap();
+        assert!(finished.output_limit_hit);
+        assert_eq!(finished.build_failure(), Some(Verdict::OutputLimit));
+        assert!(finished.elapsed < Duration::from_secs(60));
+        assert_eq!(finished.stdout.len(), MAX_OUTPUT_BYTES);
+    }
+}
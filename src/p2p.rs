@@ -1,4 +1,6 @@
 use libp2p::{identity, PeerId};
+use crate::core_types::blockchain::Block;
+use crate::core_types::encoding::Canonical;
 
     // This is a placeholder for a real P2P service.
     // A full implementation would involve setting up a Swarm, defining
@@ -9,16 +11,16 @@ use libp2p::{identity, PeerId};
     }
 
     impl P2PService {
-        pub async fn new() -> Self {
-            let local_key = identity::Keypair::generate_ed25519();
-            let peer_id = PeerId::from(local_key.public());
-            println!("[P2P] Local peer ID: {}", peer_id);
-            P2PService { peer_id }
+        pub async fn new(local_key: identity::Keypair) -> Self {
+            let service = P2PService { peer_id: PeerId::from(local_key.public()) };
+            println!("[P2P] Local peer ID: {}", service.peer_id);
+            service
         }
 
-        pub fn broadcast_block(&self, block_hash: &str) {
-            // In a real app, this would serialize the block and broadcast it
-            // to all connected peers using a protocol like Gossipsub.
-            println!("[P2P] Broadcasting new block {} to the network...", block_hash);
+        pub fn broadcast_block(&self, block: &Block) {
+            // In a real app, these canonical bytes would be broadcast to all
+            // connected peers using a protocol like Gossipsub.
+            let payload = block.to_canonical_bytes();
+            println!("[P2P] Broadcasting new block {} ({} bytes) to the network...", block.hash, payload.len());
         }
     }
\ No newline at end of file
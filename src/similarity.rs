@@ -0,0 +1,321 @@
+// Duplicate and plagiarism check for submitted solutions. Code is reduced
+// to tokens with names replaced by a placeholder, so renaming variables or
+// reformatting does not hide a copy; literals are kept, since they tell
+// solutions apart that share a shape. Overlapping runs of tokens are hashed
+// and winnowed into a fingerprint, and a submission is compared with every
+// solution to the same challenge and language on the active chain, and with
+// the challenge's reference solution. A copy whose tokens are the same as
+// the original's always matches; otherwise code too short to tell copies
+// from independent answers only matches an identical one.
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::fmt;
+use crate::challenges::challenge_trait::ChallengeSolution;
+use crate::core_types::blockchain::Block;
+
+/// Tokens per hashed run.
+const K: usize = 5;
+/// Runs per winnowing window; any copied stretch of `K + W - 1` tokens is
+/// guaranteed to share a hash with the original.
+const W: usize = 4;
+/// Solutions with fewer tokens get no winnowed hashes: any two short answers
+/// to the same challenge look alike, so they only match when identical.
+const MIN_TOKENS: usize = 30;
+/// Languages whose line comments start with `#`. Everywhere else `#` is code,
+/// such as C preprocessor lines or Rust attributes.
+const HASH_COMMENT_LANGUAGES: &[&str] = &["Python", "Ruby", "Perl", "R", "Shell", "Bash"];
+
+/// Words kept as they are, since they carry a solution's structure. Every
+/// other name becomes the same placeholder.
+const KEYWORDS: &[&str] = &[
+    "if", "else", "elif", "for", "while", "loop", "do", "in", "range", "until",
+    "return", "break", "continue", "match", "switch", "case", "when",
+    "def", "fn", "func", "fun", "function", "lambda", "let", "var", "val", "const", "mut",
+    "class", "struct", "public", "private", "static", "new", "self", "this",
+    "true", "false", "True", "False", "null", "nil", "None",
+    "and", "or", "not", "import", "export", "package", "module",
+];
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SimilarityAction {
+    /// The submission is refused.
+    Reject,
+    /// The submission is verified and mined as usual, and the match is
+    /// reported with the job. The default, since a match may be a
+    /// coincidence.
+    Flag,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SimilarityConfig {
+    /// Share of fingerprint hashes, from 0 to 1, two solutions must have in
+    /// common to count as copies.
+    pub threshold: f64,
+    pub action: SimilarityAction,
+}
+
+impl Default for SimilarityConfig {
+    fn default() -> Self {
+        SimilarityConfig { threshold: 0.8, action: SimilarityAction::Flag }
+    }
+}
+
+/// A hash of all of a solution's normalized tokens, and winnowed hashes of
+/// runs of them. The winnowed hashes are empty for code under `MIN_TOKENS`
+/// tokens, which then only matches code with the same tokens.
+pub struct Fingerprint {
+    /// `None` for code with no tokens at all.
+    whole: Option<u64>,
+    hashes: BTreeSet<u64>,
+}
+
+impl Fingerprint {
+    pub fn of(code: &str, language: &str) -> Self {
+        let tokens = normalized_tokens(code, HASH_COMMENT_LANGUAGES.contains(&language));
+        let whole = (!tokens.is_empty()).then(|| hash_run(&tokens));
+        if tokens.len() < MIN_TOKENS {
+            return Fingerprint { whole, hashes: BTreeSet::new() };
+        }
+        let runs: Vec<u64> = tokens.windows(K).map(hash_run).collect();
+        let mut hashes = BTreeSet::new();
+        for window in runs.windows(W.min(runs.len())) {
+            hashes.insert(*window.iter().min().unwrap());
+        }
+        Fingerprint { whole, hashes }
+    }
+
+    /// 1 for code with the same tokens, whatever its length. Otherwise
+    /// shared hashes as a share of the smaller fingerprint, so padding a
+    /// copy with extra code does not lower the score.
+    pub fn similarity(&self, other: &Fingerprint) -> f64 {
+        if self.whole.is_some() && self.whole == other.whole {
+            return 1.0;
+        }
+        let smaller = self.hashes.len().min(other.hashes.len());
+        if smaller == 0 {
+            return 0.0;
+        }
+        self.hashes.intersection(&other.hashes).count() as f64 / smaller as f64
+    }
+}
+
+/// Where the code a submission resembles came from.
+#[derive(Debug, Clone)]
+pub enum MatchSource {
+    Block { index: u64, hash: String, author: String },
+    Reference,
+}
+
+/// The closest earlier solution to a submission.
+#[derive(Debug, Clone)]
+pub struct SimilarityMatch {
+    pub source: MatchSource,
+    /// From 0 to 1.
+    pub score: f64,
+}
+
+impl fmt::Display for SimilarityMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = (self.score * 100.0).round();
+        match &self.source {
+            MatchSource::Block { index, hash, author } => write!(f, "{}% similar to the solution by {} in block #{} ({})", percent, author, index, hash),
+            MatchSource::Reference => write!(f, "{}% similar to the reference solution", percent),
+        }
+    }
+}
+
+pub struct SimilarityCheck {
+    threshold: f64,
+    action: SimilarityAction,
+}
+
+impl SimilarityCheck {
+    pub fn new(config: SimilarityConfig) -> Self {
+        SimilarityCheck { threshold: config.threshold, action: config.action }
+    }
+
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+
+    pub fn action(&self) -> SimilarityAction {
+        self.action
+    }
+
+    /// Compares `solution` with the earlier solutions in `chain` to the same
+    /// challenge in the same language, and with `reference`. A copy is an
+    /// error when copies are rejected; when they are flagged it is returned.
+    pub fn check(&self, solution: &ChallengeSolution, chain: &[Block], reference: Option<&str>) -> Result<Option<SimilarityMatch>, String> {
+        let Some(closest) = closest_match(solution, chain, reference) else { return Ok(None) };
+        if closest.score < self.threshold {
+            return Ok(None);
+        }
+        match self.action {
+            SimilarityAction::Reject => Err(format!("the solution is {}", closest)),
+            SimilarityAction::Flag => Ok(Some(closest)),
+        }
+    }
+}
+
+fn closest_match(solution: &ChallengeSolution, chain: &[Block], reference: Option<&str>) -> Option<SimilarityMatch> {
+    let fingerprint = Fingerprint::of(&solution.code, &solution.language);
+    let earlier = chain.iter()
+        .filter(|block| block.body.proof.challenge_id == solution.challenge_id && block.body.proof.language == solution.language)
+        .map(|block| SimilarityMatch {
+            source: MatchSource::Block { index: block.header.index, hash: block.hash.clone(), author: block.body.proof.author.clone() },
+            score: fingerprint.similarity(&Fingerprint::of(&block.body.proof.code, &solution.language)),
+        });
+    let reference = reference.map(|code| SimilarityMatch {
+        source: MatchSource::Reference,
+        score: fingerprint.similarity(&Fingerprint::of(code, &solution.language)),
+    });
+    earlier.chain(reference).max_by(|a, b| a.score.total_cmp(&b.score))
+}
+
+/// Splits code into tokens, dropping whitespace and comments: `#` comments
+/// when `hash_comments` is set, `//` and `/* */` ones otherwise. Names other
+/// than keywords become `I`; numbers and string literals are kept as they
+/// are, and every other character is a token of its own.
+fn normalized_tokens(code: &str, hash_comments: bool) -> Vec<String> {
+    let chars: Vec<char> = code.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let starts = |text: &str| text.chars().enumerate().all(|(offset, t)| chars.get(i + offset) == Some(&t));
+        if c.is_whitespace() {
+            i += 1;
+        } else if (hash_comments && c == '#') || (!hash_comments && starts("//")) {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if !hash_comments && starts("/*") {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+        } else if c == '"' || c == '\'' || c == '`' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && chars[i] != c {
+                if chars[i] == '\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            i += 1;
+            tokens.push(chars[start..i.min(chars.len())].iter().collect());
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.' || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(chars[start..i].iter().collect());
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            tokens.push(if KEYWORDS.contains(&word.as_str()) { word } else { "I".to_string() });
+        } else {
+            tokens.push(c.to_string());
+            i += 1;
+        }
+    }
+    tokens
+}
+
+/// FNV-1a over a run of tokens, so fingerprints are the same on every node.
+fn hash_run(tokens: &[String]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for token in tokens {
+        for byte in token.bytes().chain(std::iter::once(0)) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::challenges::challenge_trait::Difficulty;
+    use crate::core_types::blockchain::BlockBody;
+
+    const ORIGINAL: &str = "def solve(n):\n    a, b = 0, 1\n    for i in range(n):\n        a, b = b, a + b\n    return a\n";
+    const RENAMED: &str = "def solve(count):  # iterative\n  prev, cur = 0, 1\n  for step in range(count):\n      prev, cur = cur, prev + cur\n  return prev\n";
+
+    fn solution(code: &str) -> ChallengeSolution {
+        ChallengeSolution {
+            challenge_id: "fibonacci".to_string(),
+            language: "Python".to_string(),
+            code: code.to_string(),
+            author: "ada".to_string(),
+        }
+    }
+
+    fn chain_with(code: &str) -> Vec<Block> {
+        vec![Block::new(1, 1_700_000_000, "00".repeat(32), 1, Difficulty::Easy,
+            BlockBody { transactions: vec![], proof: solution(code), attestations: vec![] })]
+    }
+
+    fn tokens(code: &str, hash_comments: bool) -> Vec<String> {
+        normalized_tokens(code, hash_comments)
+    }
+
+    #[test]
+    fn renamed_and_reformatted_copy_matches() {
+        assert!(tokens(ORIGINAL, true).len() >= MIN_TOKENS);
+        assert_eq!(Fingerprint::of(ORIGINAL, "Python").similarity(&Fingerprint::of(RENAMED, "Python")), 1.0);
+    }
+
+    #[test]
+    fn short_code_matches_only_when_its_tokens_are_the_same() {
+        let short = "def solve(n):\n    return n * n\n";
+        let renamed = "def solve(x):  # square\n  return x*x\n";
+        let other = "def solve(n):\n    return n ** 2\n";
+        assert!(tokens(short, true).len() < MIN_TOKENS);
+        let fingerprint = Fingerprint::of(short, "Python");
+        assert_eq!(fingerprint.similarity(&Fingerprint::of(short, "Python")), 1.0);
+        assert_eq!(fingerprint.similarity(&Fingerprint::of(renamed, "Python")), 1.0);
+        assert_eq!(fingerprint.similarity(&Fingerprint::of(other, "Python")), 0.0);
+        assert_eq!(Fingerprint::of("", "Python").similarity(&Fingerprint::of("  # nothing", "Python")), 0.0);
+    }
+
+    #[test]
+    fn short_exact_copies_are_rejected() {
+        let short = "def solve(n):\n    return n * n\n";
+        let rejecting = SimilarityCheck::new(SimilarityConfig { action: SimilarityAction::Reject, ..SimilarityConfig::default() });
+        assert!(rejecting.check(&solution(short), &chain_with(short), None).unwrap_err().contains("100% similar"));
+        assert!(rejecting.check(&solution(short), &[], Some(short)).unwrap_err().contains("reference solution"));
+    }
+
+    #[test]
+    fn literals_are_kept() {
+        assert_eq!(tokens("x = 42 + \"a b\"", true), ["I", "=", "42", "+", "\"a b\""]);
+        assert_ne!(tokens("return 0", true), tokens("return 1", true));
+    }
+
+    #[test]
+    fn hash_starts_a_comment_only_in_hash_comment_languages() {
+        assert_eq!(tokens("x = 1 # note\n// y", true), ["I", "=", "1", "/", "/", "I"]);
+        assert_eq!(tokens("#include <x>\n/* a */ y; // b", false), ["#", "I", "<", "I", ">", "I", ";"]);
+    }
+
+    #[test]
+    fn copies_are_flagged_by_default_and_rejected_when_configured() {
+        let flagged = SimilarityCheck::new(SimilarityConfig::default());
+        assert_eq!(flagged.action(), SimilarityAction::Flag);
+        let found = flagged.check(&solution(RENAMED), &chain_with(ORIGINAL), None).unwrap().unwrap();
+        assert!(matches!(found.source, MatchSource::Block { index: 1, .. }));
+
+        let rejecting = SimilarityCheck::new(SimilarityConfig { action: SimilarityAction::Reject, ..SimilarityConfig::default() });
+        assert!(rejecting.check(&solution(RENAMED), &chain_with(ORIGINAL), None).unwrap_err().contains("100% similar"));
+        assert!(rejecting.check(&solution(RENAMED), &[], Some(ORIGINAL)).unwrap_err().contains("reference solution"));
+    }
+}
@@ -0,0 +1,237 @@
+// Append-only on-disk block log.
+//
+// `blocks.log` is a sequence of records, one per accepted block (active or
+// side branch), in the order they were accepted:
+//   u32 big-endian payload length | 32-byte SHA-256 of payload | payload
+// where the payload is the block's canonical encoding. The log is synced
+// after each append. On open it is scanned: a final record cut short by a
+// crash is truncated away, and a bad record anywhere else refuses the open,
+// since truncating there would throw away good blocks after it.
+use sha2::{Sha256, Digest};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use crate::core_types::blockchain::Block;
+use crate::core_types::encoding::{Canonical, DecodeError};
+
+const LOG_FILE: &str = "blocks.log";
+/// Offset index written by earlier versions; it was never read, and is
+/// removed on open.
+const OLD_INDEX_FILE: &str = "blocks.idx";
+const RECORD_HEADER_LEN: usize = 4 + 32;
+
+#[derive(Debug)]
+pub enum StoreError {
+    Io(io::Error),
+    /// A record with good records after it failed its checksum, so this is
+    /// not a torn write.
+    Corrupt { offset: u64 },
+    Decode { offset: u64, reason: DecodeError },
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Io(err) => write!(f, "block store I/O error: {}", err),
+            StoreError::Corrupt { offset } => write!(f, "block record at offset {} is corrupt", offset),
+            StoreError::Decode { offset, reason } => write!(f, "block record at offset {} could not be decoded: {}", offset, reason),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<io::Error> for StoreError {
+    fn from(err: io::Error) -> Self {
+        StoreError::Io(err)
+    }
+}
+
+pub struct BlockStore {
+    log: File,
+    records: usize,
+}
+
+impl BlockStore {
+    /// Opens the store in `dir`, creating it if needed, and returns every
+    /// stored block in append order.
+    pub fn open(dir: &Path) -> Result<(BlockStore, Vec<Block>), StoreError> {
+        fs::create_dir_all(dir)?;
+        let mut log = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(dir.join(LOG_FILE))?;
+        match fs::remove_file(dir.join(OLD_INDEX_FILE)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err.into()),
+            _ => {}
+        }
+
+        let mut data = Vec::new();
+        log.read_to_end(&mut data)?;
+
+        let mut blocks = Vec::new();
+        let mut offset = 0usize;
+        while offset < data.len() {
+            match Self::read_record(&data, offset) {
+                Some((payload, next)) => {
+                    let block = Block::from_canonical_bytes(payload)
+                        .map_err(|reason| StoreError::Decode { offset: offset as u64, reason })?;
+                    blocks.push(block);
+                    offset = next;
+                }
+                None if Self::is_tail(&data, offset) => {
+                    println!("[WARN] Block store has a torn record at offset {}; truncating it.", offset);
+                    log.set_len(offset as u64)?;
+                    log.sync_all()?;
+                    break;
+                }
+                None => return Err(StoreError::Corrupt { offset: offset as u64 }),
+            }
+        }
+        log.seek(SeekFrom::Start(offset as u64))?;
+        let records = blocks.len();
+        Ok((BlockStore { log, records }, blocks))
+    }
+
+    /// Appends one block and syncs it to disk before returning.
+    pub fn append(&mut self, block: &Block) -> Result<(), StoreError> {
+        let payload = block.to_canonical_bytes();
+        let len = u32::try_from(payload.len()).expect("Block is larger than 4 GiB");
+        let mut record = Vec::with_capacity(RECORD_HEADER_LEN + payload.len());
+        record.extend_from_slice(&len.to_be_bytes());
+        record.extend_from_slice(&Sha256::digest(&payload));
+        record.extend_from_slice(&payload);
+
+        self.log.write_all(&record)?;
+        self.log.sync_data()?;
+        self.records += 1;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records == 0
+    }
+
+    /// The payload of the record at `offset` and the offset after it, if the
+    /// record is complete and its checksum matches.
+    fn read_record(data: &[u8], offset: usize) -> Option<(&[u8], usize)> {
+        let header = data.get(offset..offset + RECORD_HEADER_LEN)?;
+        let len = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+        let start = offset + RECORD_HEADER_LEN;
+        let payload = data.get(start..start.checked_add(len)?)?;
+        if Sha256::digest(payload).as_slice() != &header[4..] {
+            return None;
+        }
+        Some((payload, start + len))
+    }
+
+    /// Whether a bad record at `offset` is the last thing in the log, which
+    /// is the only place an interrupted append can leave one. Its length
+    /// field may itself be damaged, so it only counts as the tail when it
+    /// reaches the end of the log and no good record starts after it.
+    fn is_tail(data: &[u8], offset: usize) -> bool {
+        if let Some(header) = data.get(offset..offset + RECORD_HEADER_LEN) {
+            let len = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+            if offset + RECORD_HEADER_LEN + len < data.len() {
+                return false;
+            }
+        }
+        (offset + 1..data.len()).all(|start| Self::read_record(data, start).is_none())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::challenges::challenge_trait::{ChallengeSolution, Difficulty};
+    use crate::core_types::blockchain::BlockBody;
+
+    fn block(index: u64) -> Block {
+        let proof = ChallengeSolution {
+            challenge_id: "fibonacci".to_string(),
+            language: "Python".to_string(),
+            code: format!("def solve(n): return {}", index),
+            author: "ada".to_string(),
+        };
+        Block::new(index, 1_700_000_000 + index, "0".to_string(), 1, Difficulty::Easy, BlockBody { transactions: vec![], proof, attestations: vec![] })
+    }
+
+    /// A store in a fresh directory holding blocks 1 to `count`, and the
+    /// offset each record starts at.
+    fn store_with(count: u64) -> (tempfile::TempDir, Vec<u64>) {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut store, blocks) = BlockStore::open(dir.path()).unwrap();
+        assert!(blocks.is_empty() && store.is_empty());
+        let mut offsets = Vec::new();
+        let mut offset = 0;
+        for index in 1..=count {
+            let block = block(index);
+            offsets.push(offset);
+            offset += (RECORD_HEADER_LEN + block.to_canonical_bytes().len()) as u64;
+            store.append(&block).unwrap();
+        }
+        (dir, offsets)
+    }
+
+    fn hashes(blocks: &[Block]) -> Vec<String> {
+        blocks.iter().map(|block| block.hash.clone()).collect()
+    }
+
+    fn rewrite(dir: &Path, change: impl FnOnce(&mut Vec<u8>)) {
+        let path = dir.join(LOG_FILE);
+        let mut data = fs::read(&path).unwrap();
+        change(&mut data);
+        fs::write(&path, data).unwrap();
+    }
+
+    #[test]
+    fn reopening_returns_blocks_in_append_order() {
+        let (dir, _) = store_with(3);
+        let (store, blocks) = BlockStore::open(dir.path()).unwrap();
+        assert!(!store.is_empty());
+        assert_eq!(hashes(&blocks), hashes(&[block(1), block(2), block(3)]));
+    }
+
+    #[test]
+    fn torn_tail_is_truncated_and_appending_resumes() {
+        let (dir, offsets) = store_with(3);
+        rewrite(dir.path(), |data| data.truncate(data.len() - 10));
+
+        let (mut store, blocks) = BlockStore::open(dir.path()).unwrap();
+        assert_eq!(hashes(&blocks), hashes(&[block(1), block(2)]));
+        assert_eq!(fs::metadata(dir.path().join(LOG_FILE)).unwrap().len(), offsets[2]);
+
+        store.append(&block(4)).unwrap();
+        let (_, blocks) = BlockStore::open(dir.path()).unwrap();
+        assert_eq!(hashes(&blocks), hashes(&[block(1), block(2), block(4)]));
+    }
+
+    #[test]
+    fn corrupt_middle_record_refuses_to_open() {
+        let (dir, offsets) = store_with(3);
+        let flipped = offsets[1] as usize + RECORD_HEADER_LEN + 5;
+        rewrite(dir.path(), |data| data[flipped] ^= 0xff);
+        let before = fs::read(dir.path().join(LOG_FILE)).unwrap();
+
+        assert!(matches!(BlockStore::open(dir.path()), Err(StoreError::Corrupt { offset }) if offset == offsets[1]));
+        // The good third record is still there.
+        assert_eq!(fs::read(dir.path().join(LOG_FILE)).unwrap(), before);
+    }
+
+    #[test]
+    fn corrupt_length_reaching_past_the_end_is_not_a_torn_tail() {
+        let (dir, offsets) = store_with(3);
+        let length = offsets[1] as usize;
+        rewrite(dir.path(), |data| data[length..length + 4].copy_from_slice(&u32::MAX.to_be_bytes()));
+        let before = fs::read(dir.path().join(LOG_FILE)).unwrap();
+
+        assert!(matches!(BlockStore::open(dir.path()), Err(StoreError::Corrupt { offset }) if offset == offsets[1]));
+        assert_eq!(fs::read(dir.path().join(LOG_FILE)).unwrap(), before);
+    }
+
+    #[test]
+    fn stale_index_file_is_removed() {
+        let (dir, _) = store_with(1);
+        fs::write(dir.path().join(OLD_INDEX_FILE), [0u8; 8]).unwrap();
+        BlockStore::open(dir.path()).unwrap();
+        assert!(!dir.path().join(OLD_INDEX_FILE).exists());
+    }
+}
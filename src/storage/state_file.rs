@@ -0,0 +1,244 @@
+// Node state that is not part of the chain: developer profiles, guilds and
+// the transaction queue. Blocks themselves live in the block store.
+//
+// The file carries a `schema_version`. Older layouts are brought up to date
+// one version at a time by `MIGRATIONS` before the file is deserialized.
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use crate::app_state::AppState;
+use crate::core_types::blockchain::{Blockchain, ChainError};
+use crate::core_types::legacy::LegacyBlockchain;
+use crate::core_types::transaction::Transaction;
+use crate::gamification::guilds::Guild;
+use crate::gamification::profile::DeveloperProfile;
+
+pub const STATE_PATH: &str = "devchain_state.json";
+pub const BLOCKS_DIR: &str = "devchain_blocks";
+
+/// Layout version written by this build.
+///   1: the whole node state, including a chain with `f64` amounts.
+///   2: profiles, guilds and queued transactions; blocks are in the block store.
+pub const SCHEMA_VERSION: u64 = 2;
+
+type Migration = fn(Value, &mut Option<Blockchain>) -> Result<Value, StateFileError>;
+
+/// `MIGRATIONS[n]` turns a version `n + 1` file into a version `n + 2` file.
+const MIGRATIONS: [Migration; 1] = [v1_to_v2];
+
+#[derive(Debug)]
+pub enum StateFileError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+    UnsupportedVersion(u64),
+    LegacyChain(ChainError),
+}
+
+impl fmt::Display for StateFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateFileError::Io(err) => write!(f, "{}", err),
+            StateFileError::Parse(err) => write!(f, "not valid state JSON: {}", err),
+            StateFileError::UnsupportedVersion(version) => write!(f, "schema version {} is not supported by this build (latest is {})", version, SCHEMA_VERSION),
+            StateFileError::LegacyChain(err) => write!(f, "the chain it holds could not be migrated: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for StateFileError {}
+
+#[derive(Deserialize)]
+pub struct StateFile {
+    pub profiles: HashMap<String, DeveloperProfile>,
+    pub guilds: HashMap<String, Guild>,
+    #[serde(default)]
+    pub pending_transactions: Vec<Transaction>,
+}
+
+#[derive(Serialize)]
+struct StateFileRef<'a> {
+    schema_version: u64,
+    profiles: &'a HashMap<String, DeveloperProfile>,
+    guilds: &'a HashMap<String, Guild>,
+    pending_transactions: &'a [Transaction],
+}
+
+/// A state file read from disk and brought up to `SCHEMA_VERSION`.
+pub struct LoadedState {
+    pub file: StateFile,
+    /// The chain carried by a version 1 file, for seeding the block store.
+    pub legacy_chain: Option<Blockchain>,
+    /// The version the file was written in, if it had to be migrated.
+    pub migrated_from: Option<u64>,
+}
+
+impl StateFile {
+    /// Moves the saved state into `state`. Queued transactions go through
+    /// `add_transaction` again and are dropped if the chain no longer allows them.
+    pub fn restore_into(self, state: &mut AppState) {
+        state.profiles = self.profiles;
+        state.guilds = self.guilds;
+        for tx in self.pending_transactions {
+            let _ = state.blockchain.add_transaction(tx);
+        }
+    }
+}
+
+/// Reads and migrates the state file. `Ok(None)` means there is no file yet.
+/// A file written in an older version is copied to `<path>.v<N>.bak` first.
+pub fn load() -> Result<Option<LoadedState>, StateFileError> {
+    load_from(Path::new(STATE_PATH))
+}
+
+fn load_from(path: &Path) -> Result<Option<LoadedState>, StateFileError> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(StateFileError::Io(err)),
+    };
+    let mut value: Value = serde_json::from_str(&contents).map_err(StateFileError::Parse)?;
+    let version = schema_version_of(&value)?;
+
+    let mut legacy_chain = None;
+    for migration in &MIGRATIONS[(version - 1) as usize..] {
+        value = migration(value, &mut legacy_chain)?;
+    }
+    let migrated_from = (version < SCHEMA_VERSION).then_some(version);
+    if migrated_from.is_some() {
+        fs::copy(path, format!("{}.v{}.bak", path.display(), version)).map_err(StateFileError::Io)?;
+    }
+
+    let file = serde_json::from_value(value).map_err(StateFileError::Parse)?;
+    Ok(Some(LoadedState { file, legacy_chain, migrated_from }))
+}
+
+/// Files from before the version field existed are told apart by whether
+/// they still hold the chain.
+fn schema_version_of(value: &Value) -> Result<u64, StateFileError> {
+    let version = match value.get("schema_version") {
+        Some(version) => version.as_u64().unwrap_or(0),
+        None if value.get("blockchain").is_some() => 1,
+        None => 2,
+    };
+    if version == 0 || version > SCHEMA_VERSION {
+        return Err(StateFileError::UnsupportedVersion(version));
+    }
+    Ok(version)
+}
+
+fn v1_to_v2(mut value: Value, legacy_chain: &mut Option<Blockchain>) -> Result<Value, StateFileError> {
+    let blockchain = value.as_object_mut().and_then(|object| object.remove("blockchain")).unwrap_or(Value::Null);
+    let blockchain: LegacyBlockchain = serde_json::from_value(blockchain).map_err(StateFileError::Parse)?;
+    *legacy_chain = Some(blockchain.migrate().map_err(StateFileError::LegacyChain)?);
+    value["schema_version"] = Value::from(2);
+    Ok(value)
+}
+
+/// Writes the state to a temporary file and renames it over the old one, so
+/// a crash mid-write leaves the previous file intact.
+pub fn save(state: &AppState) {
+    if let Err(err) = write_atomically(state, Path::new(STATE_PATH)) {
+        println!("[ERROR] Could not save state to {}: {}", STATE_PATH, err);
+    }
+}
+
+fn write_atomically(state: &AppState, path: &Path) -> io::Result<()> {
+    let contents = StateFileRef {
+        schema_version: SCHEMA_VERSION,
+        profiles: &state.profiles,
+        guilds: &state.guilds,
+        pending_transactions: &state.blockchain.pending_transactions,
+    };
+    let temp_path = format!("{}.tmp", path.display());
+    let mut writer = BufWriter::new(File::create(&temp_path)?);
+    serde_json::to_writer_pretty(&mut writer, &contents)?;
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    fs::rename(&temp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::challenges::registry::ChallengeRegistry;
+    use crate::core_types::amount::Amount;
+
+    const V1_FIXTURE: &str = include_str!("testdata/state_v1.json");
+
+    #[test]
+    fn v1_file_is_migrated_and_backed_up() {
+        ChallengeRegistry::install_built_in();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        fs::write(&path, V1_FIXTURE).unwrap();
+
+        let loaded = load_from(&path).unwrap().unwrap();
+        assert_eq!(loaded.migrated_from, Some(1));
+        assert_eq!(loaded.file.guilds["rustaceans"].members, ["dev_polyglot_001"]);
+        let chain = loaded.legacy_chain.unwrap();
+        assert_eq!(chain.chain.len(), 2);
+        assert_eq!(chain.validate(), Ok(()));
+        assert_eq!(chain.confirmed_accounts().balance("dev_polyglot_001"), Amount::from_dvc(100));
+        assert_eq!(fs::read_to_string(dir.path().join("state.json.v1.bak")).unwrap(), V1_FIXTURE);
+
+        // Once saved in the new layout, the file loads without migrating.
+        let mut state = AppState::new();
+        loaded.file.restore_into(&mut state);
+        write_atomically(&state, &path).unwrap();
+        let reloaded = load_from(&path).unwrap().unwrap();
+        assert_eq!(reloaded.migrated_from, None);
+        assert!(reloaded.legacy_chain.is_none());
+        assert_eq!(reloaded.file.guilds["rustaceans"].name, "Rustaceans");
+    }
+
+    #[test]
+    fn tampered_v1_chain_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        fs::write(&path, V1_FIXTURE.replace("100.0", "1000.0")).unwrap();
+        assert!(matches!(load_from(&path), Err(StateFileError::LegacyChain(ChainError::HashMismatch { index: 1 }))));
+        assert!(!dir.path().join("state.json.v1.bak").exists());
+    }
+
+    #[test]
+    fn missing_file_and_future_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        assert!(load_from(&path).unwrap().is_none());
+        fs::write(&path, r#"{ "schema_version": 3, "profiles": {}, "guilds": {} }"#).unwrap();
+        assert!(matches!(load_from(&path), Err(StateFileError::UnsupportedVersion(3))));
+    }
+
+    #[test]
+    fn interrupted_write_leaves_the_old_file_intact() {
+        ChallengeRegistry::install_built_in();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let mut state = AppState::new();
+        state.guilds.insert("old".to_string(), Guild::new("old".to_string(), "Old".to_string(), "ada".to_string()));
+        write_atomically(&state, &path).unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+
+        // A crash part way through leaves a truncated temporary file behind,
+        // which the next load ignores and the next save replaces.
+        let temp_path = dir.path().join("state.json.tmp");
+        fs::write(&temp_path, &saved[..saved.len() / 2]).unwrap();
+        assert!(load_from(&path).unwrap().unwrap().file.guilds.contains_key("old"));
+
+        // A write that fails before the rename does not touch the old file.
+        fs::remove_file(&temp_path).unwrap();
+        fs::create_dir(&temp_path).unwrap();
+        state.guilds.clear();
+        assert!(write_atomically(&state, &path).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), saved);
+
+        fs::remove_dir(&temp_path).unwrap();
+        write_atomically(&state, &path).unwrap();
+        assert!(load_from(&path).unwrap().unwrap().file.guilds.is_empty());
+        assert!(!temp_path.exists());
+    }
+}
@@ -0,0 +1,225 @@
+// Verifier quorum. A block's attestations are checked against the chain
+// rules when it is accepted; which verifiers this node trusts, and how many
+// of them must vouch for a solution, is this node's own policy on top.
+// Solutions mined here are attested by this node and, if that is not
+// enough, by the verifier peers it is configured to ask. A verifier never
+// counts towards the quorum of a block that pays it the reward, so a miner
+// cannot vouch for its own solution.
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+use crate::challenges::challenge_trait::ChallengeSolution;
+use crate::core_types::attestation::Attestation;
+use crate::core_types::blockchain::{Block, SYSTEM_SENDER};
+
+/// How long a peer may take to verify a solution and answer.
+const PEER_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct VerifierConfig {
+    /// Attestations from distinct registered verifiers a block needs. When
+    /// unset, 1 if any verifier other than this node is registered and 0
+    /// otherwise, since this node never counts towards blocks paying itself.
+    pub quorum: Option<usize>,
+    /// Addresses of the verifiers whose attestations count. This node's own
+    /// wallet always counts.
+    pub registered: Vec<String>,
+    /// API addresses (`host:port`) of verifier nodes asked to attest
+    /// solutions mined here.
+    pub peers: Vec<String>,
+}
+
+pub struct VerifierSet {
+    quorum: usize,
+    registered: BTreeSet<String>,
+    peers: Vec<String>,
+}
+
+impl VerifierSet {
+    pub fn new(config: VerifierConfig, own_address: String) -> Self {
+        let mut registered: BTreeSet<String> = config.registered.into_iter().collect();
+        let others = registered.iter().filter(|address| **address != own_address).count();
+        let quorum = config.quorum.unwrap_or(if others == 0 { 0 } else { 1 });
+        registered.insert(own_address);
+        VerifierSet { quorum, registered, peers: config.peers }
+    }
+
+    pub fn quorum(&self) -> usize {
+        self.quorum
+    }
+
+    pub fn registered_count(&self) -> usize {
+        self.registered.len()
+    }
+
+    /// Registered verifiers among `attestations` that signed a pass for
+    /// this solution and parent, each counted once. The address the block
+    /// rewards, `reward_recipient`, is left out.
+    fn attesters(&self, attestations: &[Attestation], solution: &ChallengeSolution, parent_hash: &str, reward_recipient: &str) -> BTreeSet<String> {
+        let solution_hash = solution.hash();
+        attestations.iter()
+            .filter_map(|attestation| attestation.check(&solution_hash, &solution.challenge_id, parent_hash).ok())
+            .filter(|verifier| self.registered.contains(verifier) && verifier != reward_recipient)
+            .collect()
+    }
+
+    /// Checks that a block from another node carries a quorum of attestations.
+    pub fn check_block(&self, block: &Block) -> Result<(), String> {
+        let reward_recipient = block.body.transactions.iter()
+            .find(|tx| tx.sender == SYSTEM_SENDER)
+            .map(|tx| tx.recipient.as_str())
+            .unwrap_or_default();
+        let attesters = self.attesters(&block.body.attestations, &block.body.proof, &block.header.previous_hash, reward_recipient);
+        if attesters.len() < self.quorum {
+            return Err(format!(
+                "block #{} is attested by {} registered verifier(s), {} needed",
+                block.header.index, attesters.len(), self.quorum,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Gathers attestations for a solution this node verified, starting
+    /// with its own, until the quorum is reached. The block will pay
+    /// `reward_recipient`, whose own attestation does not count.
+    pub fn collect(&self, solution: &ChallengeSolution, parent_hash: &str, reward_recipient: &str, own: Attestation) -> Result<Vec<Attestation>, String> {
+        let mut attestations = vec![own];
+        for peer in &self.peers {
+            if self.attesters(&attestations, solution, parent_hash, reward_recipient).len() >= self.quorum {
+                break;
+            }
+            println!("[VERIFIERS] Asking {} to attest the solution...", peer);
+            match request_attestation(peer, solution, parent_hash) {
+                Ok(attestation) if self.attesters(std::slice::from_ref(&attestation), solution, parent_hash, reward_recipient).is_empty() => {
+                    println!("[WARN] {} did not attest a pass as a registered verifier other than the reward recipient (verdict: {}).", peer, attestation.verdict);
+                }
+                Ok(attestation) => attestations.push(attestation),
+                Err(reason) => println!("[WARN] Could not get an attestation from {}: {}", peer, reason),
+            }
+        }
+
+        let attested = self.attesters(&attestations, solution, parent_hash, reward_recipient).len();
+        if attested < self.quorum {
+            return Err(format!("only {} of the {} required verifier attestations were collected", attested, self.quorum));
+        }
+        Ok(attestations)
+    }
+}
+
+/// Asks a peer's API (`POST /api/attestations`) to verify the solution.
+fn request_attestation(peer: &str, solution: &ChallengeSolution, parent_hash: &str) -> Result<Attestation, String> {
+    let body = serde_json::json!({ "solution": solution, "parent_hash": parent_hash }).to_string();
+    let mut stream = TcpStream::connect(peer).map_err(|err| err.to_string())?;
+    stream.set_read_timeout(Some(PEER_TIMEOUT)).map_err(|err| err.to_string())?;
+    write!(
+        stream,
+        "POST /api/attestations HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        peer, body.len(), body,
+    ).map_err(|err| err.to_string())?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).map_err(|err| err.to_string())?;
+    let (head, body) = response.split_once("\r\n\r\n").ok_or("malformed HTTP response")?;
+    let status = head.split_whitespace().nth(1).unwrap_or_default();
+    let reply: serde_json::Value = serde_json::from_str(body).map_err(|err| format!("unreadable reply: {}", err))?;
+    if status != "200" {
+        return Err(format!("HTTP {}: {}", status, reply["error"].as_str().unwrap_or("no reason given")));
+    }
+    serde_json::from_value(reply["attestation"].clone()).map_err(|err| format!("unreadable attestation: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::identity::ed25519::Keypair;
+    use crate::challenges::challenge_trait::Difficulty;
+    use crate::core_types::attestation::PASSED_VERDICT;
+    use crate::core_types::blockchain::{block_reward, BlockBody};
+    use crate::core_types::transaction::Transaction;
+
+    const PARENT: &str = "00ab";
+
+    fn address(keypair: &Keypair) -> String {
+        Transaction::address_for(&keypair.public())
+    }
+
+    fn solution() -> ChallengeSolution {
+        ChallengeSolution {
+            challenge_id: "fibonacci".to_string(),
+            language: "Python".to_string(),
+            code: "def solve(n): return n".to_string(),
+            author: "ada".to_string(),
+        }
+    }
+
+    fn pass(keypair: &Keypair) -> Attestation {
+        Attestation::new_signed(keypair, &solution().hash(), "fibonacci", PARENT, PASSED_VERDICT, "feed")
+    }
+
+    fn block(reward_recipient: &str, attestations: Vec<Attestation>) -> Block {
+        let reward = Transaction {
+            sender: SYSTEM_SENDER.to_string(),
+            recipient: reward_recipient.to_string(),
+            amount: block_reward(Difficulty::Easy),
+            public_key: String::new(),
+            nonce: 0,
+            signature: String::new(),
+        };
+        Block::new(1, 1_700_000_000, PARENT.to_string(), 1, Difficulty::Easy,
+            BlockBody { transactions: vec![reward], proof: solution(), attestations })
+    }
+
+    fn set(quorum: Option<usize>, registered: &[&Keypair], own: &Keypair) -> VerifierSet {
+        let config = VerifierConfig { quorum, registered: registered.iter().map(|keypair| address(keypair)).collect(), peers: Vec::new() };
+        VerifierSet::new(config, address(own))
+    }
+
+    #[test]
+    fn default_quorum_is_reachable_by_a_solo_node() {
+        let (own, other) = (Keypair::generate(), Keypair::generate());
+        let solo = set(None, &[], &own);
+        assert_eq!(solo.quorum(), 0);
+        assert!(solo.collect(&solution(), PARENT, &address(&own), pass(&own)).is_ok());
+        assert_eq!(set(None, &[&own], &own).quorum(), 0);
+        assert_eq!(set(None, &[&other], &own).quorum(), 1);
+    }
+
+    #[test]
+    fn check_block_counts_distinct_registered_verifiers_against_the_quorum() {
+        let (own, a, b, outsider, miner) = (Keypair::generate(), Keypair::generate(), Keypair::generate(), Keypair::generate(), Keypair::generate());
+        let verifiers = set(Some(2), &[&a, &b], &own);
+
+        let below = block(&address(&miner), vec![pass(&a), pass(&a), pass(&outsider)]);
+        assert_eq!(verifiers.check_block(&below).unwrap_err(), "block #1 is attested by 1 registered verifier(s), 2 needed");
+        assert!(verifiers.check_block(&block(&address(&miner), vec![pass(&a), pass(&b)])).is_ok());
+        assert!(verifiers.check_block(&block(&address(&miner), vec![pass(&a), pass(&b), pass(&own)])).is_ok());
+
+        let mut forged = pass(&b);
+        forged.signature = pass(&a).signature;
+        assert!(verifiers.check_block(&block(&address(&miner), vec![pass(&a), forged])).is_err());
+    }
+
+    #[test]
+    fn reward_recipient_does_not_count_towards_its_own_block() {
+        let (own, a) = (Keypair::generate(), Keypair::generate());
+        let verifiers = set(Some(1), &[&a], &own);
+        assert!(verifiers.check_block(&block(&address(&a), vec![pass(&a)])).is_err());
+        assert!(verifiers.check_block(&block(&address(&a), vec![pass(&own)])).is_ok());
+    }
+
+    #[test]
+    fn collect_stops_at_the_quorum_and_fails_below_it() {
+        let (own, other) = (Keypair::generate(), Keypair::generate());
+        let verifiers = set(Some(1), &[&other], &own);
+        assert_eq!(verifiers.collect(&solution(), PARENT, &address(&other), pass(&own)).unwrap(), vec![pass(&own)]);
+        assert_eq!(
+            verifiers.collect(&solution(), PARENT, &address(&own), pass(&own)).unwrap_err(),
+            "only 0 of the 1 required verifier attestations were collected",
+        );
+        let stricter = set(Some(2), &[&other], &own);
+        assert!(stricter.collect(&solution(), PARENT, &address(&other), pass(&own)).is_err());
+    }
+}
@@ -8,11 +8,11 @@ impl Executor {
     pub fn run_from_ledger(blockchain: &Blockchain, block_index: u64) {
         println!("\n--- Solution Ledger Executor ---");
         if let Some(block) = blockchain.chain.get(block_index as usize) {
-            println!("[VM] Found solution in Block #{}", block.index);
-            println!("[VM] Author: {}", block.proof.author);
-            println!("[VM] Language: {}", block.proof.language);
-            println!("[VM] Executing code:\n---\n{}\n---", block.proof.code);
-            println!("[VM]... Simulation complete. Royalties sent to {}.", block.proof.author);
+            println!("[VM] Found solution in Block #{}", block.header.index);
+            println!("[VM] Author: {}", block.body.proof.author);
+            println!("[VM] Language: {}", block.body.proof.language);
+            println!("[VM] Executing code:\n---\n{}\n---", block.body.proof.code);
+            println!("[VM]... Simulation complete. Royalties sent to {}.", block.body.proof.author);
         } else {
             println!("[VM-ERROR] Block #{} not found in the ledger.", block_index);
         }
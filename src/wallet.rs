@@ -0,0 +1,89 @@
+use libp2p::identity;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use crate::challenges::challenge_trait::ChallengeSolution;
+use crate::core_types::amount::Amount;
+use crate::core_types::attestation::Attestation;
+use crate::core_types::hex;
+use crate::core_types::transaction::Transaction;
+use crate::oracle::report::VerificationReport;
+
+// The node's Ed25519 key. It signs outgoing DVC transfers and this node's
+// verification attestations, and its address is where this node's block
+// rewards are paid.
+pub struct Wallet {
+    keypair: identity::ed25519::Keypair,
+}
+
+impl Wallet {
+    /// Loads the key from `path`, or creates one there if the file does not
+    /// exist. An existing file that cannot be read stops the node rather than
+    /// being replaced, since it may hold the only copy of a funded key.
+    pub fn load_or_create(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                let keypair = hex::decode(text.trim())
+                    .and_then(|mut bytes| identity::ed25519::Keypair::try_from_bytes(&mut bytes).ok());
+                let Some(keypair) = keypair else {
+                    println!("[ERROR] {} does not hold a valid wallet key. Refusing to start so it is not overwritten.", path);
+                    std::process::exit(1);
+                };
+                println!("[INFO] Wallet key loaded from {}.", path);
+                Wallet { keypair }
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                let keypair = identity::ed25519::Keypair::generate();
+                if let Err(err) = write_key_file(path, &hex::encode(&keypair.to_bytes())) {
+                    println!("[ERROR] Could not write wallet key file {}: {}", path, err);
+                    std::process::exit(1);
+                }
+                println!("[INFO] New wallet key generated and saved to {}.", path);
+                Wallet { keypair }
+            }
+            Err(err) => {
+                println!("[ERROR] Could not read wallet key file {}: {}", path, err);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    pub fn address(&self) -> String {
+        Transaction::address_for(&self.keypair.public())
+    }
+
+    pub fn identity(&self) -> identity::Keypair {
+        identity::Keypair::from(self.keypair.clone())
+    }
+
+    pub fn transfer(&self, recipient: &str, amount: Amount, nonce: u64) -> Transaction {
+        Transaction::new_signed(&self.keypair, recipient, amount, nonce)
+    }
+
+    /// Signs what this node's oracle concluded about `solution`, verified
+    /// on top of the block `parent_hash`.
+    pub fn attest(&self, solution: &ChallengeSolution, parent_hash: &str, report: &VerificationReport) -> Attestation {
+        Attestation::new_signed(
+            &self.keypair,
+            &solution.hash(),
+            &solution.challenge_id,
+            parent_hash,
+            &format!("{:?}", report.verdict),
+            &report.digest(),
+        )
+    }
+}
+
+/// Creates the key file, readable by its owner only where the platform
+/// supports it. Fails if the file already exists.
+fn write_key_file(path: &str, contents: &str) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()
+}